@@ -1,3 +1,5 @@
+use intcode::batch::Batch;
+use intcode::*;
 use std::io::*;
 
 fn main() {
@@ -8,46 +10,26 @@ fn main() {
         .unwrap()
         .unwrap()
         .split(',')
-        .map(|w| w.parse::<u32>().unwrap())
+        .map(|w| w.parse::<i64>().unwrap())
         .collect::<Vec<_>>();
 
-    for n in 0..100 {
-        for v in 0..100 {
-            let mut ints = ints.clone();
-            ints[1] = n;
-            ints[2] = v;
+    let mut program = Computer::new(ints);
+    program.set_isa(Isa::Day2);
 
-            if compute(ints) == 19690720 {
-                println!("{}", 100 * n + v);
-                return;
-            }
-        }
-    }
-}
-
-
-fn compute(mut ints: Vec<u32>) -> u32 {
-    for i in (0..).step_by(4) {
-        let op = ints[i];
-        if op == 99 {
-            break;
-        }
-
-        let a = ints[ints[i + 1] as usize];
-        let b = ints[ints[i + 2] as usize];
+    let mut batch = Batch::new(&program);
+    batch.set_prepare(|computer, input| {
+        computer.poke(1, input[0]).unwrap();
+        computer.poke(2, input[1]).unwrap();
+    });
+    batch.set_finish(|computer, output| output.push(computer.peek(0)));
+    batch.set_stop(|_, output| output == [19690720]);
 
-        let res = if op == 1 {
-            a + b
-        } else if op == 2 {
-            a * b
-        } else {
-            unreachable!();
-        };
-
-        let target = ints[i + 3] as usize;
-        ints[target] = res;
+    let inputs = (0..100)
+        .flat_map(|noun| (0..100).map(move |verb| vec![noun, verb]))
+        .collect::<Vec<_>>();
+    let outputs = batch.run(inputs.clone()).unwrap();
+    if outputs.last() == Some(&vec![19690720]) {
+        let input = &inputs[outputs.len() - 1];
+        println!("{}", 100 * input[0] + input[1]);
     }
-
-    ints[0]
 }
-
@@ -1,3 +1,4 @@
+use intcode::pipeline::best_phases;
 use intcode::*;
 use std::fs::*;
 
@@ -9,14 +10,14 @@ fn main() {
     println!("Maximum Thrustor: {}", best);
 }
 
-fn best_combination(code: Vec<i32>) -> i32 {
-    combinations((5..=9).collect())
-        .map(|combination| feedback_amps(&code, combination))
-        .max()
+fn best_combination(code: Vec<i64>) -> i64 {
+    let (_, signal) = best_phases(&Computer::new(code), &[5, 6, 7, 8, 9], true)
         .unwrap()
+        .expect("Insufficient input");
+    signal
 }
 
-fn read_code() -> Vec<i32> {
+fn read_code() -> Vec<i64> {
     let text = read_to_string("input").unwrap();
     let mut lines = text.lines();
 
@@ -28,58 +29,6 @@ fn read_code() -> Vec<i32> {
         .collect()
 }
 
-fn combinations(values: Vec<i32>) -> impl Iterator<Item = Vec<i32>> {
-    (0..values.len()).flat_map(move |i| -> Box<dyn Iterator<Item = _>> {
-        let mut values = values.clone();
-        let last = values.swap_remove(i);
-
-        if values.is_empty() {
-            Box::new(Some(vec![last]).into_iter())
-        } else {
-            let combs = combinations(values).map(move |mut combination: Vec<i32>| {
-                combination.push(last);
-                combination
-            });
-
-            Box::new(combs)
-        }
-    })
-}
-
-fn feedback_amps(code: &Vec<i32>, phases: Vec<i32>) -> i32 {
-    let mut input = 0;
-    let mut last = 0;
-
-    let mut amps = (0..5)
-        .map(|_| Computer::new(code.to_vec()))
-        .collect::<Vec<_>>();
-
-    for (amp, phase) in amps.iter_mut().zip(phases) {
-        amp.provide_input(Some(phase));
-    }
-
-    loop {
-        for amp in &mut amps {
-            amp.provide_input(Some(input));
-            match amp.run() {
-                Action::Halt => return last,
-                Action::Output(output) => input = output,
-                Action::NeedsInput => panic!("Insufficient input"),
-            }
-        }
-
-        last = input;
-    }
-}
-
-#[test]
-fn simple_combination() {
-    assert_eq!(
-        combinations(vec![1, 2]).collect::<Vec<_>>(),
-        vec![vec![2, 1], vec![1, 2],],
-    );
-}
-
 #[test]
 fn example_1() {
     let code = vec![
@@ -3,13 +3,6 @@ use std::collections::*;
 use std::fs;
 use std::io::{stdin, stdout, Write};
 
-#[derive(Debug, Copy, Clone)]
-enum Command {
-    Draw { point: Point, tile: Tile },
-    Score(i64),
-    PollInput,
-}
-
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 struct Point {
     x: i64,
@@ -25,109 +18,110 @@ enum Tile {
     Ball,
 }
 
-fn main() {
-    let code = load_code();
-    let arcade = Computer::new(code);
-
-    let mut grid = HashMap::new();
-
-    let io = arcade.run_async();
+struct Cabinet {
+    grid: HashMap<Point, Tile>,
+    output: Vec<i64>,
+    ball: Point,
+    paddle: Point,
+    auto: usize,
+    ticks: usize,
+}
 
-    let next_command = || -> Option<Command> {
-        let x = io.recv().ok()?;
+impl OutputSink for Cabinet {
+    fn output(&mut self, value: i64) {
+        self.output.push(value);
+        if self.output.len() < 3 {
+            return;
+        }
 
-        match x {
-            Action::Halt => None,
-            Action::NeedsInput => Some(Command::PollInput),
-            Action::Output(x) => {
-                let y = io.recv().ok()?.output();
-                let t = io.recv().ok()?.output();
+        let (x, y, t) = (self.output[0], self.output[1], self.output[2]);
+        self.output.clear();
 
-                if x == -1 && y == 0 {
-                    Some(Command::Score(t))
-                } else {
-                    let point = Point { x, y };
-                    let tile = match t {
-                        0 => Tile::Empty,
-                        1 => Tile::Wall,
-                        2 => Tile::Block,
-                        3 => Tile::Horizontal,
-                        4 => Tile::Ball,
-                        _ => unreachable!(),
-                    };
-
-                    Some(Command::Draw { point, tile })
-                }
-            }
+        if x == -1 && y == 0 {
+            println!("Score: {}", t);
+            return;
         }
-    };
-
-    let mut ball = Point { x: 0, y: 0 };
-    let mut paddle = Point { x: 0, y: 0 };
 
-    let mut auto = 0;
-    let mut ticks = 0;
+        let point = Point { x, y };
+        let tile = match t {
+            0 => Tile::Empty,
+            1 => Tile::Wall,
+            2 => Tile::Block,
+            3 => Tile::Horizontal,
+            4 => Tile::Ball,
+            _ => unreachable!(),
+        };
+        self.grid.insert(point, tile);
+
+        match tile {
+            Tile::Ball => self.ball = point,
+            Tile::Horizontal => self.paddle = point,
+            _ => {}
+        }
+    }
+}
 
-    while let Some(command) = next_command() {
-        match command {
-            Command::Draw { point, tile } => {
-                grid.insert(point, tile);
+impl InputSource for Cabinet {
+    fn next_input(&mut self) -> Option<i64> {
+        let mut line = String::new();
+        display_grid(&self.grid);
+        let dir = loop {
+            if self.auto > 0 {
+                self.auto -= 1;
+                let dx = self.paddle.x - self.ball.x;
+                break if dx == 0 {
+                    0
+                } else if dx > 0 {
+                    -1
+                } else {
+                    1
+                };
+            } else {
+                print!("Enter some input: ");
+                stdout().lock().flush().unwrap();
+
+                stdin().read_line(&mut line).unwrap();
+                let text = line.trim();
+                if let Ok(count) = text.parse::<usize>() {
+                    self.auto = count;
+                    continue;
+                };
 
-                match tile {
-                    Tile::Ball => ball = point,
-                    Tile::Horizontal => paddle = point,
-                    _ => {}
-                }
-            }
-            Command::Score(score) => {
-                println!("Score: {}", score);
-            }
-            Command::PollInput => {
-                let mut line = String::new();
-                display_grid(&grid);
-                let dir = loop {
-                    if auto > 0 {
-                        auto -= 1;
-                        let dx = paddle.x - ball.x;
-                        break if dx == 0 {
-                            0
-                        } else if dx > 0 {
-                            -1
-                        } else {
-                            1
-                        };
-                    } else {
-                        print!("Enter some input: ");
-                        stdout().lock().flush().unwrap();
-
-                        stdin().read_line(&mut line).unwrap();
-                        let text = line.trim();
-                        if let Ok(count) = text.parse::<usize>() {
-                            auto = count;
-                            continue;
-                        };
-
-                        break match text {
-                            "l" => -1,
-                            "r" => 1,
-                            "n" => 0,
-                            "" => {
-                                auto = 1;
-                                continue;
-                            }
-                            _ => continue,
-                        };
+                break match text {
+                    "l" => -1,
+                    "r" => 1,
+                    "n" => 0,
+                    "" => {
+                        self.auto = 1;
+                        continue;
                     }
+                    _ => continue,
                 };
-
-                io.send(dir).unwrap();
-                ticks += 1;
             }
-        }
+        };
+
+        self.ticks += 1;
+        Some(dir)
     }
+}
+
+fn main() {
+    let code = load_code();
+    let mut arcade = Computer::new(code);
+
+    let mut cabinet = Cabinet {
+        grid: HashMap::new(),
+        output: Vec::new(),
+        ball: Point { x: 0, y: 0 },
+        paddle: Point { x: 0, y: 0 },
+        auto: 0,
+        ticks: 0,
+    };
+
+    arcade.run_device(&mut cabinet);
 
-    println!("Game over! ({} ticks)", ticks);
-    display_grid(&grid);
+    println!("Game over! ({} ticks)", cabinet.ticks);
+    display_grid(&cabinet.grid);
 }
 
 fn load_code() -> Vec<i64> {
@@ -1,12 +1,16 @@
+use intcode::batch::Batch;
 use intcode::*;
 
 fn main() {
     let computer = Computer::load("input").unwrap();
 
+    let points = (0..50).flat_map(|y| (0..50).map(move |x| vec![x, y]));
+    let outputs = Batch::new(&computer).run(points).unwrap();
+
     let mut points = 0;
-    for y in 0..50 {
-        for x in 0..50 {
-            if affected([x, y], &computer) {
+    for row in outputs.chunks(50) {
+        for output in row {
+            if affected(output) {
                 print!("#");
                 points += 1;
             } else {
@@ -19,12 +23,9 @@ fn main() {
     println!("Points: {}", points);
 }
 
-fn affected([x, y]: [i64; 2], computer: &Computer) -> bool {
-    let mut instance = computer.clone();
-    instance.provide_input(vec![x, y]);
-    match instance.run() {
-        Action::NeedsInput => panic!("insufficient input"),
-        Action::Halt => panic!("program terminated"),
-        Action::Output(value) => value == 1,
+fn affected(output: &[i64]) -> bool {
+    match output {
+        [value] => *value == 1,
+        _ => panic!("expected a single output, got {:?}", output),
     }
 }
@@ -1,8 +1,7 @@
-use intcode::*;
-use std::convert::*;
+use intcode::ascii::AsciiComputer;
 
 fn main() {
-    let mut droid = Computer::load("input").unwrap();
+    let mut droid = AsciiComputer::load("input").unwrap();
 
 
     let instructions = [
@@ -25,24 +24,13 @@ fn main() {
         "RUN",
     ];
 
-    let program = instructions.join("\n") + "\n";
-
-    droid.provide_input(program.bytes().map(|c| c as i64));
-
-    loop {
-        match droid.run() {
-            Action::Halt => break,
-            Action::NeedsInput => panic!("insufficent input"),
-            Action::Output(value) => {
-                match u8::try_from(value) {
-                    Ok(ch) => match ch as char {
-                        '\n' => println!(),
-                        ch => print!("{}", ch),
-                    }
-                    Err(_) => println!("{}", value),
-                }
-            }
-        }
+    for instruction in &instructions {
+        droid.send_line(instruction);
     }
-}
 
+    let response = droid.read_all().unwrap();
+    print!("{}", response.text);
+    for value in response.values {
+        println!("{}", value);
+    }
+}
@@ -1,69 +1,46 @@
+use intcode::network::*;
 use intcode::*;
-use std::collections::*;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-struct Packet {
-    x: i64,
-    y: i64,
+/// Remembers the last packet sent to address 255, and sends it to address 0 whenever the network
+/// is idle.
+#[derive(Default)]
+struct Nat {
+    packet: Option<Packet>,
+    previous: Option<i64>,
 }
 
-fn main() {
-    let mut computers = (0..50)
-        .map(|address| {
-            let mut computer = Computer::load("input").unwrap();
-            computer.provide_input(Some(address));
-            computer
-        })
-        .collect::<Vec<_>>();
-
-    let mut queues = vec![VecDeque::<Packet>::new(); 50];
-    let mut nat = None;
-    let mut previous = None;
+impl Router for Nat {
+    type Output = i64;
 
-    loop {
-        let mut idle = true;
-        for (i, computer) in computers.iter_mut().enumerate() {
-            let mut waiting = false;
-            loop {
-                match computer.run() {
-                    Action::Halt => break,
-                    Action::NeedsInput => match queues[i].pop_front() {
-                        Some(packet) => {
-                            idle = false;
-                            computer.provide_input(vec![packet.x, packet.y]);
-                        }
-                        None => {
-                            computer.provide_input(Some(-1));
-                            if waiting {
-                                break;
-                            }
-                            waiting = true;
-                        }
-                    },
-                    Action::Output(target) => {
-                        idle = false;
-                        let x = computer.run().output();
-                        let y = computer.run().output();
-                        let packet = Packet { x, y };
-                        if target == 255 {
-                            nat = Some(packet);
-                        } else {
-                            queues[target as usize].push_back(packet);
-                        }
-                    }
-                }
-            }
+    fn route(&mut self, packet: Packet, _outbox: &mut Vec<Packet>) -> Option<i64> {
+        if packet.destination == 255 {
+            self.packet = Some(packet);
         }
+        None
+    }
 
-        if idle && queues.iter().all(|queue| queue.is_empty()) {
-            queues[0].push_back(nat.expect("NAT had no packet"));
-            if nat == previous {
-                println!("Got NAT: {}", nat.unwrap().y);
-                break;
-            }
-            previous = nat;
+    fn idle(&mut self, outbox: &mut Vec<Packet>) -> Option<i64> {
+        let packet = self.packet.expect("NAT had no packet");
+        if self.previous == Some(packet.y) {
+            return Some(packet.y);
         }
+        self.previous = Some(packet.y);
+
+        outbox.push(Packet {
+            source: 255,
+            destination: 0,
+            ..packet
+        });
+        None
     }
+}
+
+fn main() {
+    let computer = Computer::load("input").unwrap();
+    let mut network = Network::new(&computer, 50);
+
+    let y = network.run(&mut Nat::default()).unwrap();
+    println!("Got NAT: {}", y);
 
     println!("Done.");
 }
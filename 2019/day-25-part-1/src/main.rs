@@ -1,12 +1,11 @@
 use intcode::*;
-use std::fs;
 use std::io::{self, BufRead, Write};
-use std::mem;
 use std::path::PathBuf;
 use structopt::*;
 
 #[derive(Debug, StructOpt)]
 struct Options {
+    /// Replay a transcript saved by an earlier session before reading commands
     #[structopt(short, long)]
     fast_forward: Option<PathBuf>,
 }
@@ -21,62 +20,62 @@ fn main() {
     let options = Options::from_args();
 
     let mut computer = Computer::load("input").unwrap();
-
-    let mut transcript = String::new();
-    let mut prompt = Vec::new();
-
-    let stdin = io::stdin();
-
-    let mut lines: Box<dyn Iterator<Item = String>> = Box::new(None.into_iter());
+    computer.set_history(true);
+    // Checkpoints are history lengths, which only work while no history is forgotten
+    computer.set_history_limit(None);
+    computer.set_recording(true);
 
     if let Some(path) = options.fast_forward {
-        let file = fs::read_to_string(&path).unwrap();
-        lines = Box::new(
-            lines
-                .chain(file.lines().map(|line| line.to_owned()))
-                .collect::<Vec<_>>()
-                .into_iter(),
-        );
+        let transcript = Transcript::load(&path).unwrap();
+        // Moving between rooms takes far fewer instructions than this
+        if let Err(error) = computer.replay(&transcript, Some(10_000_000)) {
+            eprintln!("{}", error);
+            return;
+        }
+        let output = transcript.outputs().map(|value| value as u8 as char);
+        print!("{}", output.collect::<String>());
     }
 
-    let mut lines = lines.chain(stdin.lock().lines().map(|line| line.unwrap()));
+    let mut checkpoints = Vec::new();
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines().map(|line| line.unwrap());
 
     loop {
         match computer.run() {
             Action::Halt => break,
             Action::NeedsInput => {
+                let _ = computer.transcript().unwrap().save("transcript");
+
                 let command: String = lines.next().unwrap();
-                prompt.push(command.clone());
 
                 if let Some(command) = parse_command(&command) {
                     match command {
                         Command::Rewind => {
-                            prompt.pop();
-                            prompt.pop();
-                            computer = Computer::load("input").unwrap();
-                            for command in &prompt {
-                                send_command(&mut computer, &command);
+                            if let Some(checkpoint) = checkpoints.pop() {
+                                computer.rewind_to(checkpoint);
+                                computer.clear_input();
                             }
                         }
                         Command::Weight(command) => {
+                            checkpoints.push(computer.history_len());
                             find_weight(&mut computer, &command);
                         }
                     }
                 } else {
+                    checkpoints.push(computer.history_len());
                     send_command(&mut computer, &command);
                 }
-
-                let _ = fs::write("prompt", prompt.join("\n"));
             }
             Action::Output(value) => {
                 let ch = value as u8 as char;
                 print!("{}", ch);
                 io::stdout().lock().flush().unwrap();
-                transcript.push(ch);
             }
         }
     }
 
+    let _ = computer.transcript().unwrap().save("transcript");
     println!("Done.");
 }
 
@@ -110,27 +109,27 @@ fn find_weight(computer: &mut Computer, command: &str) {
         let max = 1 << items.len();
 
         for dropped in 0..max {
-            let mut instance = computer.clone();
+            let checkpoint = computer.history_len();
             for (i, item) in items.iter().enumerate() {
                 let mask = 1 << i;
                 if dropped & mask != 0 {
-                    send_command(&mut instance, &format!("drop {}", item));
+                    send_command(computer, &format!("drop {}", item));
                 }
             }
 
-            if run_until_input(&mut instance).is_none() {
-                continue;
-            }
-
-            send_command(&mut instance, "inv");
-            send_command(&mut instance, command);
-            if let Some(output) = run_until_input(&mut instance) {
-                if !output.contains("heavier") && !output.contains("lighter") {
-                    println!("{}", output);
-                    mem::swap(computer, &mut instance);
-                    return;
+            if run_until_input(computer).is_some() {
+                send_command(computer, "inv");
+                send_command(computer, command);
+                if let Some(output) = run_until_input(computer) {
+                    if !output.contains("heavier") && !output.contains("lighter") {
+                        println!("{}", output);
+                        return;
+                    }
                 }
             }
+
+            computer.rewind_to(checkpoint);
+            computer.clear_input();
         }
     }
 }
@@ -0,0 +1,99 @@
+//! Measures what cloning a computer for every query costs, now that clones share their memory
+//! until it is written.
+//!
+//! Before memory was shared, cloning a computer copied its whole memory. That copy is measured
+//! here as copying a vector the size of the program image, next to the clone that replaced it.
+//! The scan at the end only uses `Computer::clone`, so running this benchmark on an older checkout
+//! gives the time of the same scan with deep clones.
+//!
+//! Run with `cargo bench --bench clone`.
+
+use intcode::asm::assemble;
+use intcode::{Action, Computer};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Outputs whether `(x, y)` is inside a beam, like the day 19 drone system. The program is padded
+/// with data to the given number of cells.
+fn beam(size: usize) -> Vec<i64> {
+    let mut code = assemble(
+        "
+                in [x]
+                in [y]
+                mul [x], #3, [a]
+                mul [y], #2, [b]
+                slt [a], [b], [result]
+                mul [y], #3, [a]
+                mul [x], #2, [b]
+                slt [a], [b], [a]
+                add [result], [a], [result]
+                seq [result], #0, [result]
+                out [result]
+                hlt
+        x:      data 0
+        y:      data 0
+        a:      data 0
+        b:      data 0
+        result: data 0
+        ",
+    )
+    .unwrap();
+    code.resize(size, 0);
+    code
+}
+
+fn main() {
+    for &size in &[500, 4000, 20000] {
+        let code = beam(size);
+        let program = Computer::new(code.clone());
+
+        println!("10000 queries on a {} cell program:", size);
+        let (copied, _) = measure(|| {
+            for _ in 0..10000 {
+                black_box(code.clone());
+            }
+        });
+        let (cloned, _) = measure(|| {
+            for _ in 0..10000 {
+                black_box(program.clone());
+            }
+        });
+        let (scanned, points) = measure(|| scan(&program));
+        assert!(points > 0);
+
+        println!("  deep copies:  {:>8.2?}", copied);
+        println!("  clones:       {:>8.2?}", cloned);
+        println!(
+            "  speedup:      {:>8.2}x",
+            copied.as_secs_f64() / cloned.as_secs_f64()
+        );
+        println!("  full scan:    {:>8.2?}", scanned);
+    }
+}
+
+/// Counts the points affected by the beam in a 100x100 area, asking a clone about every point.
+fn scan(program: &Computer) -> usize {
+    let mut points = 0;
+    for y in 0..100 {
+        for x in 0..100 {
+            let mut computer = program.clone();
+            computer.provide_input(vec![x, y]);
+            if let Action::Output(1) = computer.run() {
+                points += 1;
+            }
+        }
+    }
+    points
+}
+
+fn measure<T>(mut f: impl FnMut() -> T) -> (Duration, T) {
+    // Warm up, then keep the fastest of a few runs
+    let mut result = f();
+    let mut best = Duration::from_secs(u64::MAX);
+    for _ in 0..5 {
+        let start = Instant::now();
+        result = f();
+        best = best.min(start.elapsed());
+    }
+    (best, result)
+}
@@ -0,0 +1,82 @@
+//! Compares the interpreter with and without the decoded-instruction cache.
+//!
+//! Run with `cargo bench --bench decode_cache`.
+
+use intcode::asm::assemble;
+use intcode::{Action, Computer};
+use std::time::{Duration, Instant};
+
+/// Sums `i * j` for all `i, j < n`, using the relative base as scratch space.
+const NESTED_LOOPS: &str = "
+            in [n]
+            rb #vars
+            add #0, #0, rb[0]
+    outer:  add #0, #0, rb[1]
+    inner:  mul rb[0], rb[1], [product]
+            add [sum], [product], [sum]
+            add rb[1], #1, rb[1]
+            slt rb[1], [n], [loop]
+            jnz [loop], #inner
+            add rb[0], #1, rb[0]
+            slt rb[0], [n], [loop]
+            jnz [loop], #outer
+            out [sum]
+            hlt
+    n:       data 0
+    product: data 0
+    sum:     data 0
+    loop:    data 0
+    vars:    data 0, 0
+";
+
+fn main() {
+    let program = Computer::new(assemble(NESTED_LOOPS).unwrap());
+
+    println!("long-running program (n = 2000):");
+    compare(|cached| {
+        let mut computer = program.clone();
+        computer.set_decode_cache(cached);
+        computer.provide_input(Some(2000));
+        computer.run().output()
+    });
+
+    println!("many short queries from a fresh copy (n = 20, 5000 times):");
+    compare(|cached| {
+        let mut total = 0;
+        for _ in 0..5000 {
+            let mut computer = program.clone();
+            computer.set_decode_cache(cached);
+            computer.provide_input(Some(20));
+            if let Action::Output(value) = computer.run() {
+                total += value;
+            }
+        }
+        total
+    });
+}
+
+fn compare(mut run: impl FnMut(bool) -> i64) {
+    // Warm up, then alternate between the two so that noise affects both the same way, and keep
+    // the fastest run of each
+    let expected = run(false);
+    assert_eq!(run(true), expected);
+    let mut plain = Duration::from_secs(u64::MAX);
+    let mut cached = Duration::from_secs(u64::MAX);
+    for _ in 0..10 {
+        plain = plain.min(measure(|| assert_eq!(run(false), expected)));
+        cached = cached.min(measure(|| assert_eq!(run(true), expected)));
+    }
+
+    println!("  interpreter:  {:>8.2?}", plain);
+    println!("  decode cache: {:>8.2?}", cached);
+    println!(
+        "  speedup:      {:>8.2}x",
+        plain.as_secs_f64() / cached.as_secs_f64()
+    );
+}
+
+fn measure(f: impl FnOnce()) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
@@ -0,0 +1,514 @@
+//! Static analysis of programs: basic blocks, the control-flow graph between them, and the
+//! functions that can be recognized from how they are called.
+//!
+//! Only instructions that can be reached by following constant jumps from the start of the
+//! program are analyzed. Puzzle programs call functions by storing a return address relative to
+//! the relative base and jumping to the function, which ends by jumping through that address.
+//! Those return addresses are followed too, so most of a program is usually found.
+
+use crate::disasm::decode_at;
+use crate::{Address, Computer, Instruction, Parameter};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+
+pub struct Analysis {
+    pub instructions: BTreeMap<usize, Instruction>,
+    /// Basic blocks, by their first address.
+    pub blocks: BTreeMap<usize, Block>,
+    /// Functions, by their entry address.
+    pub functions: BTreeMap<usize, Function>,
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub start: usize,
+    /// Address just past the last instruction in the block.
+    pub end: usize,
+    pub exit: Exit,
+}
+
+/// How control leaves a basic block.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Exit {
+    Halt,
+    /// Continues into the next block, which starts because something else jumps to it.
+    Fallthrough(usize),
+    Jump(usize),
+    Branch {
+        taken: usize,
+        fallthrough: usize,
+    },
+    /// Jumps to a function after storing the address it should return to.
+    Call {
+        function: usize,
+        returns: usize,
+    },
+    /// Jumps through an address stored relative to the relative base.
+    Return,
+    /// Jumps to an address that cannot be determined statically.
+    Computed {
+        fallthrough: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub entry: usize,
+    /// The amount the function moves the relative base by when it is entered.
+    pub frame: Option<i64>,
+    /// Addresses of the blocks that call the function.
+    pub callers: Vec<usize>,
+    /// Blocks that belong to the function, not counting the functions it calls.
+    pub blocks: Vec<usize>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// An instruction writes into memory that holds another instruction. Only writes to a fixed
+    /// position are checked, since where a write relative to the relative base lands depends on
+    /// the relative base when it runs.
+    SelfModifying { instruction: usize, target: usize },
+    /// Control reaches memory that does not hold a valid instruction.
+    Undecodable { address: usize },
+}
+
+pub fn analyze(code: &[i64]) -> Analysis {
+    let instructions = discover(code);
+    let blocks = split_blocks(code, &instructions);
+    let functions = find_functions(&instructions, &blocks);
+
+    let mut warnings = Vec::new();
+    for (&address, &instruction) in &instructions {
+        let target = match instruction.parameters().last() {
+            Some(&Parameter::Address(Address::Position(target))) if writes(instruction) => target,
+            _ => continue,
+        };
+        if target < 0 {
+            continue;
+        }
+        let overlaps = match instructions.range(..=target as usize).next_back() {
+            Some((&start, other)) => target as usize - start < other.size(),
+            None => false,
+        };
+        if overlaps {
+            warnings.push(Warning::SelfModifying {
+                instruction: address,
+                target: target as usize,
+            });
+        }
+    }
+    let mut undecodable = BTreeSet::new();
+    for block in blocks.values() {
+        for successor in block.exit.successors() {
+            if !instructions.contains_key(&successor) {
+                undecodable.insert(successor);
+            }
+        }
+    }
+    warnings.extend(
+        undecodable
+            .into_iter()
+            .map(|address| Warning::Undecodable { address }),
+    );
+
+    Analysis {
+        instructions,
+        blocks,
+        functions,
+        warnings,
+    }
+}
+
+impl Computer {
+    pub fn analyze(&self) -> Analysis {
+        analyze(&self.memory.dense_to_vec())
+    }
+}
+
+/// Find every instruction reachable from the start of the program, following jumps with constant
+/// targets. Constants stored by `add`/`mul` are also treated as entry points, since that is how
+/// programs push return addresses before calling a function.
+pub(crate) fn discover(code: &[i64]) -> BTreeMap<usize, Instruction> {
+    let mut instructions = BTreeMap::new();
+    let mut pending = vec![0];
+
+    let entry = |value: i64, pending: &mut Vec<usize>| {
+        if 0 <= value && (value as usize) < code.len() {
+            pending.push(value as usize);
+        }
+    };
+
+    while let Some(address) = pending.pop() {
+        if instructions.contains_key(&address) {
+            continue;
+        }
+        let instruction = match decode_at(code, address) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+        instructions.insert(address, instruction);
+
+        let next = address + instruction.size();
+        match instruction {
+            Instruction::Halt => {}
+            Instruction::Jnz(_, target) | Instruction::Jez(_, target) => {
+                if let Parameter::Value(target) = target {
+                    entry(target, &mut pending);
+                }
+                if !unconditional(instruction) {
+                    pending.push(next);
+                }
+            }
+            _ => {
+                if let Some(value) = stored_constant(instruction) {
+                    entry(value, &mut pending);
+                }
+                pending.push(next);
+            }
+        }
+    }
+
+    instructions
+}
+
+fn split_blocks(
+    code: &[i64],
+    instructions: &BTreeMap<usize, Instruction>,
+) -> BTreeMap<usize, Block> {
+    let mut leaders = BTreeSet::new();
+    leaders.insert(0);
+    for (&address, &instruction) in instructions {
+        let next = address + instruction.size();
+        match instruction {
+            Instruction::Jnz(_, target) | Instruction::Jez(_, target) => {
+                match target {
+                    Parameter::Value(target) if target >= 0 => {
+                        leaders.insert(target as usize);
+                    }
+                    _ => {}
+                }
+                leaders.insert(next);
+            }
+            Instruction::Halt => {
+                leaders.insert(next);
+            }
+            _ => {
+                if let Some(value) = stored_constant(instruction) {
+                    if 0 <= value && (value as usize) < code.len() {
+                        leaders.insert(value as usize);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut blocks = BTreeMap::new();
+    let mut current: Vec<(usize, Instruction)> = Vec::new();
+
+    for (&address, &instruction) in instructions {
+        if let Some(&(last, previous)) = current.last() {
+            let next = last + previous.size();
+            if next != address || leaders.contains(&address) {
+                let block = finish_block(&current, Exit::Fallthrough(next));
+                blocks.insert(block.start, block);
+                current.clear();
+            }
+        }
+
+        current.push((address, instruction));
+
+        if is_jump(instruction) || instruction == Instruction::Halt {
+            let block = finish_block(&current, exit(&current));
+            blocks.insert(block.start, block);
+            current.clear();
+        }
+    }
+
+    if let Some(&(last, previous)) = current.last() {
+        let block = finish_block(&current, Exit::Fallthrough(last + previous.size()));
+        blocks.insert(block.start, block);
+    }
+
+    blocks
+}
+
+fn finish_block(instructions: &[(usize, Instruction)], exit: Exit) -> Block {
+    let (start, _) = instructions[0];
+    let (last, instruction) = instructions[instructions.len() - 1];
+    Block {
+        start,
+        end: last + instruction.size(),
+        exit,
+    }
+}
+
+/// The exit of a block that ends with a jump or `hlt`.
+fn exit(instructions: &[(usize, Instruction)]) -> Exit {
+    let (last, instruction) = instructions[instructions.len() - 1];
+    let next = last + instruction.size();
+
+    let target = match instruction {
+        Instruction::Jnz(_, target) | Instruction::Jez(_, target) => target,
+        _ => return Exit::Halt,
+    };
+
+    match (unconditional(instruction), target) {
+        (true, Parameter::Value(target)) if target >= 0 => {
+            // A call stores its return address on the stack earlier in the same block
+            let returns = instructions.iter().rev().find_map(|&(_, instruction)| {
+                match (
+                    instruction.parameters().last(),
+                    stored_constant(instruction),
+                ) {
+                    (Some(Parameter::Address(Address::Relative(_))), Some(value)) if value >= 0 => {
+                        Some(value as usize)
+                    }
+                    _ => None,
+                }
+            });
+            match returns {
+                Some(returns) => Exit::Call {
+                    function: target as usize,
+                    returns,
+                },
+                None => Exit::Jump(target as usize),
+            }
+        }
+        (false, Parameter::Value(target)) if target >= 0 => Exit::Branch {
+            taken: target as usize,
+            fallthrough: next,
+        },
+        (true, Parameter::Address(Address::Relative(_))) => Exit::Return,
+        (true, _) => Exit::Computed { fallthrough: None },
+        (false, _) => Exit::Computed {
+            fallthrough: Some(next),
+        },
+    }
+}
+
+fn find_functions(
+    instructions: &BTreeMap<usize, Instruction>,
+    blocks: &BTreeMap<usize, Block>,
+) -> BTreeMap<usize, Function> {
+    let mut functions = BTreeMap::new();
+
+    for block in blocks.values() {
+        if let Exit::Call { function, .. } = block.exit {
+            let frame = match instructions.get(&function) {
+                Some(Instruction::Reb(Parameter::Value(frame))) => Some(*frame),
+                _ => None,
+            };
+            functions
+                .entry(function)
+                .or_insert_with(|| Function {
+                    entry: function,
+                    frame,
+                    callers: Vec::new(),
+                    blocks: Vec::new(),
+                })
+                .callers
+                .push(block.start);
+        }
+    }
+
+    for function in functions.values_mut() {
+        let mut seen = BTreeSet::new();
+        let mut pending = vec![function.entry];
+        while let Some(start) = pending.pop() {
+            let block = match blocks.get(&start) {
+                Some(block) if seen.insert(start) => block,
+                _ => continue,
+            };
+            match block.exit {
+                Exit::Call { returns, .. } => pending.push(returns),
+                exit => pending.extend(exit.successors()),
+            }
+        }
+        function.blocks = seen.into_iter().collect();
+    }
+
+    functions
+}
+
+/// The value written by an `add` or `mul` of two constants.
+fn stored_constant(instruction: Instruction) -> Option<i64> {
+    match instruction {
+        Instruction::Add(Parameter::Value(a), Parameter::Value(b), _) => a.checked_add(b),
+        Instruction::Mul(Parameter::Value(a), Parameter::Value(b), _) => a.checked_mul(b),
+        _ => None,
+    }
+}
+
+fn unconditional(instruction: Instruction) -> bool {
+    match instruction {
+        Instruction::Jnz(Parameter::Value(value), _) => value != 0,
+        Instruction::Jez(Parameter::Value(value), _) => value == 0,
+        _ => false,
+    }
+}
+
+fn is_jump(instruction: Instruction) -> bool {
+    matches!(instruction, Instruction::Jnz(..) | Instruction::Jez(..))
+}
+
+fn writes(instruction: Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Add(..)
+            | Instruction::Mul(..)
+            | Instruction::Slt(..)
+            | Instruction::Seq(..)
+            | Instruction::Input(..)
+    )
+}
+
+impl Exit {
+    /// Blocks that control may continue to directly. Calls continue to the function, not to the
+    /// return address.
+    pub fn successors(self) -> Vec<usize> {
+        match self {
+            Exit::Halt | Exit::Return | Exit::Computed { fallthrough: None } => vec![],
+            Exit::Fallthrough(next) | Exit::Jump(next) => vec![next],
+            Exit::Computed {
+                fallthrough: Some(next),
+            } => vec![next],
+            Exit::Branch { taken, fallthrough } => vec![taken, fallthrough],
+            Exit::Call { function, .. } => vec![function],
+        }
+    }
+}
+
+impl Analysis {
+    /// Render the control-flow graph in Graphviz DOT format. Calls are drawn as an edge to the
+    /// function and a dashed edge to the return address.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "digraph intcode {{");
+        let _ = writeln!(out, "    node [shape=box, fontname=monospace];");
+
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for (address, instruction) in self.instructions.range(block.start..block.end) {
+                let _ = write!(label, "{:>6}: {}\\l", address, instruction);
+            }
+            let _ = writeln!(out, "    b{} [label=\"{}\"];", block.start, label);
+        }
+
+        for block in self.blocks.values() {
+            let mut edge = |target: usize, attributes: &str| {
+                let _ = writeln!(out, "    b{} -> b{}{};", block.start, target, attributes);
+            };
+            match block.exit {
+                Exit::Branch { taken, fallthrough } => {
+                    edge(taken, " [label=taken]");
+                    edge(fallthrough, "");
+                }
+                Exit::Call { function, returns } => {
+                    edge(function, " [label=call]");
+                    edge(returns, " [style=dashed]");
+                }
+                exit => {
+                    for successor in exit.successors() {
+                        edge(successor, "");
+                    }
+                }
+            }
+        }
+
+        let _ = writeln!(out, "}}");
+        out
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::SelfModifying {
+                instruction,
+                target,
+            } => write!(
+                f,
+                "Instruction at {} writes to code at {}",
+                instruction, target
+            ),
+            Warning::Undecodable { address } => {
+                write!(f, "Control reaches invalid instruction at {}", address)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::test_support::CALLS;
+
+    #[test]
+    fn blocks_and_calls() {
+        let analysis = analyze(&assemble(CALLS).unwrap());
+
+        let exits = analysis
+            .blocks
+            .values()
+            .map(|block| (block.start, block.exit))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            exits,
+            vec![
+                (0, Exit::Fallthrough(2)),
+                (
+                    2,
+                    Exit::Branch {
+                        taken: 23,
+                        fallthrough: 7
+                    }
+                ),
+                (
+                    7,
+                    Exit::Call {
+                        function: 24,
+                        returns: 18
+                    }
+                ),
+                (18, Exit::Jump(2)),
+                (23, Exit::Halt),
+                (24, Exit::Return),
+            ]
+        );
+
+        assert_eq!(
+            analysis.functions.values().collect::<Vec<_>>(),
+            vec![&Function {
+                entry: 24,
+                frame: Some(1),
+                callers: vec![7],
+                blocks: vec![24],
+            }]
+        );
+        assert!(analysis.warnings.is_empty());
+
+        let dot = analysis.to_dot();
+        assert!(dot.contains("b7 -> b24 [label=call];"));
+        assert!(dot.contains("b7 -> b18 [style=dashed];"));
+        assert!(dot.contains("    24: rb #1\\l"));
+    }
+
+    #[test]
+    fn warnings() {
+        // Patches its own output instruction, then jumps past the end of the program
+        let code = vec![1101, 7, 0, 5, 104, 0, 1105, 1, 100];
+        let analysis = analyze(&code);
+        assert_eq!(
+            analysis.warnings,
+            vec![
+                Warning::SelfModifying {
+                    instruction: 0,
+                    target: 5
+                },
+                Warning::Undecodable { address: 100 },
+            ]
+        );
+    }
+}
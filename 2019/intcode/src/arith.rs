@@ -0,0 +1,329 @@
+//! What happens when arithmetic overflows.
+//!
+//! By default `add`, `mul` and `rb` wrap around, in every build profile. With
+//! [`Arithmetic::Checked`] an overflow faults with [`Fault::Overflow`] instead. With
+//! [`Arithmetic::Big`], `add` and `mul` produce exact results of any size, which can be stored in
+//! memory and used by further `add`, `mul`, `slt` and `seq` instructions. Using such a value in any
+//! other way, for example as an output or an address, faults with [`Fault::Overflow`].
+
+use crate::{Computer, Fault, Instruction, Observer, Parameter};
+use std::cmp::Ordering;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Arithmetic {
+    #[default]
+    Wrapping,
+    Checked,
+    Big,
+}
+
+impl Arithmetic {
+    pub(crate) fn add(self, a: i64, b: i64) -> Result<i64, Fault> {
+        match self {
+            Arithmetic::Wrapping => Ok(a.wrapping_add(b)),
+            Arithmetic::Checked | Arithmetic::Big => a.checked_add(b).ok_or(Fault::Overflow),
+        }
+    }
+
+    pub(crate) fn mul(self, a: i64, b: i64) -> Result<i64, Fault> {
+        match self {
+            Arithmetic::Wrapping => Ok(a.wrapping_mul(b)),
+            Arithmetic::Checked | Arithmetic::Big => a.checked_mul(b).ok_or(Fault::Overflow),
+        }
+    }
+}
+
+impl Computer {
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.arithmetic = arithmetic;
+        if arithmetic != Arithmetic::Big {
+            self.wide.clear();
+        }
+    }
+
+    pub fn arithmetic(&self) -> Arithmetic {
+        self.arithmetic
+    }
+
+    /// Execute an instruction that may involve values that do not fit in 64 bits. Returns `false`
+    /// if the instruction should be executed as usual.
+    pub(crate) fn execute_big(
+        &mut self,
+        address: usize,
+        instruction: Instruction,
+        observer: &mut impl Observer,
+    ) -> Result<bool, Fault> {
+        // Wide values can not be decoded as part of an instruction
+        let mut cells = address..address + instruction.size();
+        if !self.wide.is_empty() && cells.any(|index| self.wide.contains_key(&index)) {
+            return Err(Fault::Overflow);
+        }
+
+        let (a, b, target) = match instruction {
+            Instruction::Add(a, b, target)
+            | Instruction::Mul(a, b, target)
+            | Instruction::Slt(a, b, target)
+            | Instruction::Seq(a, b, target) => (a, b, target),
+            _ => return Ok(false),
+        };
+
+        let lhs = self.operand(a, observer)?;
+        let rhs = self.operand(b, observer)?;
+        let result = match instruction {
+            Instruction::Add(..) => lhs.add(&rhs),
+            Instruction::Mul(..) => lhs.mul(&rhs),
+            Instruction::Slt(..) => BigInt::from(lhs < rhs),
+            _ => BigInt::from(lhs == rhs),
+        };
+
+        let index = self.index(target)?;
+        self.write::<true>(result.truncate(), target, observer)?;
+        if result.to_i64().is_none() {
+            self.wide.insert(index, result);
+        }
+        Ok(true)
+    }
+
+    fn operand(&mut self, param: Parameter, observer: &mut impl Observer) -> Result<BigInt, Fault> {
+        if let Parameter::Address(address) = param {
+            let index = self.index(address)?;
+            if let Some(value) = self.wide.get(&index) {
+                let value = value.clone();
+                observer.read(index, self.memory.get(index));
+                return Ok(value);
+            }
+        }
+        self.evaluate::<true>(param, observer).map(BigInt::from)
+    }
+}
+
+/// A signed integer of any size, stored as its sign and the 32-bit digits of its magnitude, least
+/// significant first, without leading zeros.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    pub(crate) fn new(negative: bool, mut magnitude: Vec<u32>) -> BigInt {
+        while magnitude.last() == Some(&0) {
+            magnitude.pop();
+        }
+        BigInt {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    /// The sign, and the digits of the magnitude, as passed to [`BigInt::new`].
+    pub(crate) fn parts(&self) -> (bool, &[u32]) {
+        (self.negative, &self.magnitude)
+    }
+
+    pub(crate) fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+        let magnitude = self.low_bits();
+        if !self.negative {
+            return if magnitude <= i64::MAX as u64 {
+                Some(magnitude as i64)
+            } else {
+                None
+            };
+        }
+        if magnitude <= 1 << 63 {
+            Some((magnitude as i64).wrapping_neg())
+        } else {
+            None
+        }
+    }
+
+    /// The lowest 64 bits, as a two's complement value.
+    pub(crate) fn truncate(&self) -> i64 {
+        let value = self.low_bits() as i64;
+        if self.negative {
+            value.wrapping_neg()
+        } else {
+            value
+        }
+    }
+
+    fn low_bits(&self) -> u64 {
+        let digit = |i: usize| u64::from(self.magnitude.get(i).copied().unwrap_or(0));
+        digit(0) | digit(1) << 32
+    }
+
+    fn add(&self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(
+                self.negative,
+                add_magnitudes(&self.magnitude, &other.magnitude),
+            );
+        }
+        match compare_magnitudes(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::new(
+                other.negative,
+                sub_magnitudes(&other.magnitude, &self.magnitude),
+            ),
+            _ => BigInt::new(
+                self.negative,
+                sub_magnitudes(&self.magnitude, &other.magnitude),
+            ),
+        }
+    }
+
+    fn mul(&self, other: &BigInt) -> BigInt {
+        let mut product = vec![0u32; self.magnitude.len() + other.magnitude.len()];
+        for (i, &a) in self.magnitude.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.magnitude.iter().enumerate() {
+                let digit = u64::from(product[i + j]) + u64::from(a) * u64::from(b) + carry;
+                product[i + j] = digit as u32;
+                carry = digit >> 32;
+            }
+            product[i + other.magnitude.len()] = carry as u32;
+        }
+        BigInt::new(self.negative != other.negative, product)
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> BigInt {
+        let magnitude = value.unsigned_abs();
+        BigInt::new(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl From<bool> for BigInt {
+    fn from(value: bool) -> BigInt {
+        BigInt::from(value as i64)
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.magnitude, &other.magnitude),
+            (true, true) => compare_magnitudes(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+    for i in 0..a.len().max(b.len()) {
+        let digit = |x: &[u32]| u64::from(x.get(i).copied().unwrap_or(0));
+        let value = digit(a) + digit(b) + carry;
+        sum.push(value as u32);
+        carry = value >> 32;
+    }
+    sum.push(carry as u32);
+    sum
+}
+
+/// `a - b`, where `a` is at least as large as `b`.
+fn sub_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, &digit) in a.iter().enumerate() {
+        let mut value = i64::from(digit) - i64::from(b.get(i).copied().unwrap_or(0)) - borrow;
+        borrow = 0;
+        if value < 0 {
+            value += 1 << 32;
+            borrow = 1;
+        }
+        difference.push(value as u32);
+    }
+    difference
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::program;
+    use crate::Action;
+
+    /// Computes `x * 4 + x * -4 + 5` with `x = 2^62`, where both products overflow, comparing the
+    /// first product with `x` along the way. Outputs the result, then the first product.
+    const PRODUCTS: &str = "
+                mul [x], #4, [y]
+                mul [x], #-4, [z]
+                slt [x], [y], [less]
+                add [y], [z], [sum]
+                add [sum], #5, [sum]
+                out [less]
+                out [sum]
+                out [y]
+                hlt
+        x:      data 4611686018427387904
+        y:      data 0
+        z:      data 0
+        less:   data 0
+        sum:    data 0
+    ";
+
+    #[test]
+    fn policies() {
+        let program = program(PRODUCTS);
+
+        let mut computer = program.clone();
+        assert_eq!(computer.arithmetic(), Arithmetic::Wrapping);
+        assert_eq!(computer.run().output(), 0);
+        assert_eq!(computer.run().output(), 5);
+        assert_eq!(computer.run().output(), 0);
+
+        let mut computer = program.clone();
+        computer.set_arithmetic(Arithmetic::Checked);
+        let error = computer.try_run().unwrap_err();
+        assert_eq!(error.fault, Fault::Overflow);
+        assert_eq!(error.instruction, 0);
+
+        let mut computer = program.clone();
+        computer.set_arithmetic(Arithmetic::Big);
+        assert_eq!(computer.run().output(), 1);
+        assert_eq!(computer.run().output(), 5);
+        let error = computer.try_run().unwrap_err();
+        assert_eq!(error.fault, Fault::Overflow);
+        assert_eq!(error.instruction, 24);
+
+        // Overwriting a wide value makes it usable again
+        computer.poke(28, 7).unwrap();
+        assert_eq!(computer.run(), Action::Output(7));
+    }
+
+    #[test]
+    fn big_integers() {
+        let big = |value: i64| BigInt::from(value);
+        let max = big(i64::MAX);
+        let min = big(i64::MIN);
+
+        let square = max.mul(&max);
+        assert_eq!(square.to_i64(), None);
+        assert_eq!(square.truncate(), 1);
+        assert!(square > max);
+        assert!(min.mul(&max) < min);
+        assert_eq!(square.add(&min.mul(&max)).add(&max), big(0));
+
+        assert_eq!(max.add(&big(1)).to_i64(), None);
+        assert_eq!(min.add(&big(-1)).to_i64(), None);
+        assert_eq!(min.add(&big(-1)).add(&big(1)).to_i64(), Some(i64::MIN));
+        assert_eq!(big(-5).add(&big(3)), big(-2));
+    }
+}
@@ -0,0 +1,162 @@
+//! Talking to programs that use ASCII text for their input and output, like the ones in days
+//! 17, 21 and 25.
+
+use crate::{Action, Computer, IntcodeError, Result};
+use std::fmt;
+use std::path::Path;
+
+#[derive(Clone)]
+pub struct AsciiComputer {
+    computer: Computer,
+}
+
+/// Everything a program printed before it stopped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Response {
+    pub text: String,
+    /// Outputs outside the ASCII range, such as the amount of dust collected in day 17.
+    pub values: Vec<i64>,
+    pub halted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsciiError {
+    Fault(IntcodeError),
+    /// The program asked for input before halting. Contains everything printed until then.
+    NeedsInput(Response),
+}
+
+impl AsciiComputer {
+    pub fn new(computer: Computer) -> AsciiComputer {
+        AsciiComputer { computer }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<AsciiComputer> {
+        Computer::load(path).map(AsciiComputer::new)
+    }
+
+    pub fn computer(&self) -> &Computer {
+        &self.computer
+    }
+
+    pub fn computer_mut(&mut self) -> &mut Computer {
+        &mut self.computer
+    }
+
+    pub fn into_inner(self) -> Computer {
+        self.computer
+    }
+
+    /// Send a line of text, followed by a newline.
+    pub fn send_line(&mut self, line: &str) {
+        self.send_text(line);
+        self.send_text("\n");
+    }
+
+    pub fn send_text(&mut self, text: &str) {
+        self.computer.provide_input(text.bytes().map(i64::from));
+    }
+
+    /// Run until the program asks for input or halts.
+    pub fn read_until_prompt(&mut self) -> Result<Response, IntcodeError> {
+        let mut response = Response::default();
+
+        loop {
+            match self.computer.try_run()? {
+                Action::Output(value) => response.push(value),
+                Action::NeedsInput => return Ok(response),
+                Action::Halt => {
+                    response.halted = true;
+                    return Ok(response);
+                }
+            }
+        }
+    }
+
+    /// Run until the program halts, failing if it asks for more input.
+    pub fn read_all(&mut self) -> Result<Response, AsciiError> {
+        let response = self.read_until_prompt().map_err(AsciiError::Fault)?;
+        if response.halted {
+            Ok(response)
+        } else {
+            Err(AsciiError::NeedsInput(response))
+        }
+    }
+}
+
+impl Response {
+    fn push(&mut self, value: i64) {
+        if (0..128).contains(&value) {
+            self.text.push(value as u8 as char);
+        } else {
+            self.values.push(value);
+        }
+    }
+}
+
+impl fmt::Display for AsciiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsciiError::Fault(error) => write!(f, "{}", error),
+            AsciiError::NeedsInput(_) => write!(f, "Program needs more input"),
+        }
+    }
+}
+
+impl std::error::Error for AsciiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::program;
+
+    /// Greets the user by name, then prints the length of the name as a non-ASCII value.
+    const GREETER: &str = r#"
+                rb #prompt
+        print:  jez rb[0], #read
+                out rb[0]
+                rb #1
+                jnz #1, #print
+        read:   in [ch]
+                seq [ch], #10, [done]
+                jnz [done], #greet
+                add [length], #1, [length]
+                jnz #1, #read
+        greet:  add [length], #1000, [length]
+                out #72
+                out #105
+                out #10
+                out [length]
+                hlt
+        ch:     data 0
+        done:   data 0
+        length: data 0
+        prompt: string "Name?\n"
+                data 0
+    "#;
+
+    #[test]
+    fn conversation() {
+        let mut computer = AsciiComputer::new(program(GREETER));
+
+        let prompt = computer.read_until_prompt().unwrap();
+        assert_eq!(prompt.text, "Name?\n");
+        assert!(!prompt.halted);
+
+        assert!(matches!(
+            computer.clone().read_all(),
+            Err(AsciiError::NeedsInput(_))
+        ));
+
+        computer.send_line("Ferris");
+        let response = computer.read_all().unwrap();
+        assert_eq!(
+            response,
+            Response {
+                text: "Hi\n".to_owned(),
+                values: vec![1006],
+                halted: true,
+            }
+        );
+    }
+}
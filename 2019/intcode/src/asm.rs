@@ -0,0 +1,453 @@
+//! A small assembly language for Intcode, using the same syntax as the listings produced by
+//! [`disasm`](crate::disasm):
+//!
+//! ```text
+//! ; print the numbers 3, 2, 1
+//!         add #3, #0, [count]
+//! loop:   out [count]
+//!         add [count], #-1, [count]
+//!         jnz [count], #loop
+//!         hlt
+//! count:  data 0
+//! hello:  string "Hello!\n"
+//! ```
+//!
+//! Operands are either positional (`[12]`), immediate (`#5`) or relative to the relative base
+//! (`rb[-3]`), and may refer to labels with an optional offset (`#loop`, `[count+1]`). A leading
+//! numeric address such as `12:` is ignored, so disassembler listings can be assembled again.
+
+use crate::{Address, Instruction, Parameter};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Instruction(&'static str, Vec<Operand>),
+    Data(Vec<Expr>),
+    String(Vec<i64>),
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+#[derive(Debug, Clone)]
+struct Operand {
+    mode: Mode,
+    expr: Expr,
+}
+
+/// A sum of signed terms, such as `loop+2` or `-1`.
+#[derive(Debug, Clone)]
+struct Expr {
+    /// Each term, and whether it is subtracted.
+    terms: Vec<(bool, Term)>,
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+    /// The magnitude of a number, so that `-9223372036854775808` can be written.
+    Number(u64),
+    Label(String),
+}
+
+const MNEMONICS: &[(&str, usize)] = &[
+    ("add", 3),
+    ("mul", 3),
+    ("in", 1),
+    ("out", 1),
+    ("jnz", 2),
+    ("jez", 2),
+    ("slt", 3),
+    ("seq", 3),
+    ("rb", 1),
+    ("hlt", 0),
+];
+
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut items = Vec::new();
+    let mut labels = HashMap::new();
+    let mut address = 0;
+
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let error = |message: String| AsmError { line, message };
+
+        let mut text = strip_comment(text).trim();
+
+        while let Some(colon) = text.find(':') {
+            let name = text[..colon].trim();
+            if name.parse::<usize>().is_ok() {
+                // address from a disassembler listing
+            } else if is_identifier(name) {
+                if labels.insert(name.to_owned(), address).is_some() {
+                    return Err(error(format!("duplicate label `{}`", name)));
+                }
+            } else {
+                break;
+            }
+            text = text[colon + 1..].trim();
+        }
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let (word, rest) = match text.find(char::is_whitespace) {
+            Some(end) => (&text[..end], text[end..].trim()),
+            None => (text, ""),
+        };
+
+        let item = match word {
+            "data" => Item::Data(
+                split_operands(rest)
+                    .into_iter()
+                    .map(parse_expr)
+                    .collect::<Result<_, _>>()
+                    .map_err(error)?,
+            ),
+            "string" => Item::String(parse_string(rest).map_err(error)?),
+            _ => {
+                let &(mnemonic, arity) =
+                    MNEMONICS
+                        .iter()
+                        .find(|(mnemonic, _)| *mnemonic == word)
+                        .ok_or_else(|| error(format!("unknown mnemonic `{}`", word)))?;
+
+                let operands = split_operands(rest)
+                    .into_iter()
+                    .map(parse_operand)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+
+                if operands.len() != arity {
+                    return Err(error(format!(
+                        "`{}` expects {} operands, found {}",
+                        mnemonic,
+                        arity,
+                        operands.len()
+                    )));
+                }
+
+                Item::Instruction(mnemonic, operands)
+            }
+        };
+
+        address += item.size();
+        items.push((line, item));
+    }
+
+    let mut code = Vec::with_capacity(address);
+
+    for (line, item) in items {
+        let error = |message: String| AsmError { line, message };
+
+        match item {
+            Item::Data(values) => {
+                for value in values {
+                    code.push(value.resolve(&labels).map_err(error)?);
+                }
+            }
+            Item::String(values) => code.extend(values),
+            Item::Instruction(mnemonic, operands) => {
+                let parameters = operands
+                    .iter()
+                    .map(|operand| operand.resolve(&labels))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+                let instruction = build_instruction(mnemonic, &parameters).map_err(error)?;
+                code.extend(instruction.encode());
+            }
+        }
+    }
+
+    Ok(code)
+}
+
+fn build_instruction(mnemonic: &str, parameters: &[Parameter]) -> Result<Instruction, String> {
+    let target = |parameter: Parameter| {
+        parameter
+            .address()
+            .ok_or_else(|| format!("`{}` cannot write to an immediate operand", mnemonic))
+    };
+
+    let instruction = match (mnemonic, parameters) {
+        ("add", &[a, b, c]) => Instruction::Add(a, b, target(c)?),
+        ("mul", &[a, b, c]) => Instruction::Mul(a, b, target(c)?),
+        ("in", &[a]) => Instruction::Input(target(a)?),
+        ("out", &[a]) => Instruction::Output(a),
+        ("jnz", &[a, b]) => Instruction::Jnz(a, b),
+        ("jez", &[a, b]) => Instruction::Jez(a, b),
+        ("slt", &[a, b, c]) => Instruction::Slt(a, b, target(c)?),
+        ("seq", &[a, b, c]) => Instruction::Seq(a, b, target(c)?),
+        ("rb", &[a]) => Instruction::Reb(a),
+        ("hlt", &[]) => Instruction::Halt,
+        _ => unreachable!("operand count is checked while parsing"),
+    };
+
+    Ok(instruction)
+}
+
+impl Item {
+    fn size(&self) -> usize {
+        match self {
+            Item::Instruction(_, operands) => 1 + operands.len(),
+            Item::Data(values) => values.len(),
+            Item::String(values) => values.len(),
+        }
+    }
+}
+
+impl Operand {
+    fn resolve(&self, labels: &HashMap<String, usize>) -> Result<Parameter, String> {
+        let value = self.expr.resolve(labels)?;
+        let parameter = match self.mode {
+            Mode::Position => Parameter::Address(Address::Position(value)),
+            Mode::Immediate => Parameter::Value(value),
+            Mode::Relative => Parameter::Address(Address::Relative(value)),
+        };
+        Ok(parameter)
+    }
+}
+
+impl Expr {
+    fn resolve(&self, labels: &HashMap<String, usize>) -> Result<i64, String> {
+        let mut sum = 0i64;
+        for &(negative, ref term) in &self.terms {
+            let value = match term {
+                Term::Number(value) => *value,
+                Term::Label(name) => *labels
+                    .get(name)
+                    .ok_or_else(|| format!("undefined label `{}`", name))?
+                    as u64,
+            };
+            let next = if negative {
+                sum.checked_sub_unsigned(value)
+            } else {
+                sum.checked_add_unsigned(value)
+            };
+            sum = next.ok_or_else(|| "value does not fit in 64 bits".to_owned())?;
+        }
+        Ok(sum)
+    }
+}
+
+fn strip_comment(text: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, ch) in text.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &text[..i],
+            _ => {}
+        }
+    }
+    text
+}
+
+fn split_operands(text: &str) -> Vec<&str> {
+    if text.is_empty() {
+        Vec::new()
+    } else {
+        text.split(',').map(str::trim).collect()
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(ch) if ch.is_ascii_alphabetic() || ch == '_' => {}
+        _ => return false,
+    }
+    chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '.')
+}
+
+fn parse_operand(text: &str) -> Result<Operand, String> {
+    let (mode, inner) = if let Some(value) = text.strip_prefix('#') {
+        (Mode::Immediate, value)
+    } else if let Some(inner) = text.strip_prefix("rb[").and_then(|t| t.strip_suffix(']')) {
+        (Mode::Relative, inner)
+    } else if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        (Mode::Position, inner)
+    } else {
+        return Err(format!(
+            "invalid operand `{}`, expected `[address]`, `#value` or `rb[offset]`",
+            text
+        ));
+    };
+
+    Ok(Operand {
+        mode,
+        expr: parse_expr(inner)?,
+    })
+}
+
+fn parse_expr(text: &str) -> Result<Expr, String> {
+    let mut terms = Vec::new();
+    let mut rest = text.trim();
+
+    let mut negative = false;
+    if let Some(tail) = rest.strip_prefix('-') {
+        negative = true;
+        rest = tail.trim_start();
+    }
+
+    loop {
+        let end = rest.find(['+', '-']).unwrap_or(rest.len());
+        let word = rest[..end].trim();
+
+        let term = if !word.is_empty() && word.chars().all(|ch| ch.is_ascii_digit()) {
+            let value = word
+                .parse()
+                .map_err(|_| format!("number `{}` does not fit in 64 bits", word))?;
+            Term::Number(value)
+        } else if is_identifier(word) {
+            Term::Label(word.to_owned())
+        } else {
+            return Err(format!("invalid expression `{}`", text));
+        };
+        terms.push((negative, term));
+
+        match rest[end..].chars().next() {
+            None => break,
+            Some(op) => {
+                negative = op == '-';
+                rest = rest[end + 1..].trim_start();
+            }
+        }
+    }
+
+    Ok(Expr { terms })
+}
+
+fn parse_string(text: &str) -> Result<Vec<i64>, String> {
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .ok_or_else(|| format!("expected a quoted string, found `{}`", text))?;
+
+    let mut values = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        let ch = if ch == '\\' {
+            match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('0') => '\0',
+                Some('\\') => '\\',
+                Some('"') => '"',
+                other => {
+                    return Err(format!(
+                        "invalid escape sequence `\\{}`",
+                        other.unwrap_or(' ')
+                    ))
+                }
+            }
+        } else {
+            ch
+        };
+        values.push(ch as i64);
+    }
+
+    Ok(values)
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute, disasm};
+
+    #[test]
+    fn countdown() {
+        let code = assemble(
+            "
+            ; print the numbers 3, 2, 1
+                    add #3, #0, [count]
+            loop:   out [count]
+                    add [count], #-1, [count]
+                    jnz [count], #loop
+                    hlt
+            count:  data 0
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(compute(code, None), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn strings_and_offsets() {
+        let code = assemble(
+            "
+                    rb #text
+            loop:   jez rb[0], #end
+                    out rb[0]
+                    rb #1
+                    jnz #1, #loop
+            end:    hlt
+            text:   string \"hi; \\\"you\\\"\\n\"
+                    data 0, text-end+2
+            ",
+        )
+        .unwrap();
+
+        let output = compute(code.clone(), None);
+        let text = output
+            .iter()
+            .map(|&ch| ch as u8 as char)
+            .collect::<String>();
+        assert_eq!(text, "hi; \"you\"\n");
+        assert_eq!(code.last(), Some(&3));
+    }
+
+    #[test]
+    fn round_trip() {
+        let code = vec![109, 1, 21101, 12, -3, 0, 4, 7, 99, 5, 10, 199, 3];
+        let listing = disasm::disassemble(&code).to_string();
+        assert_eq!(assemble(&listing).unwrap(), code);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |source| assemble(source).unwrap_err();
+
+        assert_eq!(error("hlt\nfoo #1").line, 2);
+        assert_eq!(error("add #1, #2").line, 1);
+        assert_eq!(error("add #1, #2, #3").line, 1);
+        assert_eq!(error("jnz #1, #nowhere").line, 1);
+        assert_eq!(error("a: hlt\na: hlt").line, 2);
+        assert_eq!(error("out 12").line, 1);
+        assert_eq!(error("data 9223372036854775807+1").line, 1);
+        assert_eq!(error("data 18446744073709551616").line, 1);
+    }
+
+    #[test]
+    fn extreme_values() {
+        let code = vec![i64::MIN, i64::MAX, -1];
+        assert_eq!(
+            assemble("data -9223372036854775808, 9223372036854775807, -1").unwrap(),
+            code
+        );
+        let listing = disasm::disassemble(&code).to_string();
+        assert_eq!(assemble(&listing).unwrap(), code);
+    }
+}
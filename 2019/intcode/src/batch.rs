@@ -0,0 +1,280 @@
+//! Asking the same program many independent questions in parallel.
+//!
+//! Every query runs on a clone of the program with its own input, until the program halts. The
+//! clones share the program image, so starting a query costs little more than the memory it
+//! writes to.
+
+use crate::{Action, Computer, IntcodeError};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+/// Number of instructions a query executes between checks for whether it is still needed.
+const SLICE: u64 = 10_000;
+
+/// Runs a query for each of a sequence of inputs on clones of one program, on several threads.
+pub struct Batch {
+    program: Computer,
+    threads: usize,
+    max_steps: Option<u64>,
+    stop: Option<Predicate>,
+    prepare: Option<Prepare>,
+    finish: Option<Finish>,
+}
+
+/// Called with the input and output of a query.
+type Predicate = Box<dyn Fn(&[i64], &[i64]) -> bool + Send + Sync>;
+
+/// Called with the computer and the input of a query before it runs.
+type Prepare = Box<dyn Fn(&mut Computer, &[i64]) + Send + Sync>;
+
+/// Called with the computer and the output of a query after it halts.
+type Finish = Box<dyn Fn(&Computer, &mut Vec<i64>) + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchError {
+    /// The query with this index needed more input than it was given.
+    NeedsInput(usize),
+    Fault(usize, IntcodeError),
+    /// The query with this index executed more instructions than allowed.
+    StepLimit(usize),
+}
+
+impl Batch {
+    pub fn new(program: &Computer) -> Batch {
+        Batch {
+            program: program.clone(),
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            max_steps: None,
+            stop: None,
+            prepare: None,
+            finish: None,
+        }
+    }
+
+    /// Run at most this many queries at the same time. Defaults to the available parallelism.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Fail with [`BatchError::StepLimit`] if a query executes more than this many instructions.
+    pub fn set_max_steps(&mut self, max_steps: Option<u64>) {
+        self.max_steps = max_steps;
+    }
+
+    /// Stop at the first query, in order, for which `predicate` returns `true` when called with
+    /// its input and output. Queries after it are not started, or are cancelled while running.
+    pub fn set_stop(&mut self, predicate: impl Fn(&[i64], &[i64]) -> bool + Send + Sync + 'static) {
+        self.stop = Some(Box::new(predicate));
+    }
+
+    /// Prepare the computer of every query with `prepare`, for example by writing the input to
+    /// memory, instead of providing the input to the program.
+    pub fn set_prepare(&mut self, prepare: impl Fn(&mut Computer, &[i64]) + Send + Sync + 'static) {
+        self.prepare = Some(Box::new(prepare));
+    }
+
+    /// Call `finish` with the computer and the output of every query that halts, for example to
+    /// add a result the program left in memory to the output.
+    pub fn set_finish(
+        &mut self,
+        finish: impl Fn(&Computer, &mut Vec<i64>) + Send + Sync + 'static,
+    ) {
+        self.finish = Some(Box::new(finish));
+    }
+
+    /// Run a query for every input, returning what each one output in the order of the inputs.
+    pub fn run<I>(&self, inputs: I) -> Result<Vec<Vec<i64>>, BatchError>
+    where
+        I: IntoIterator<Item = Vec<i64>>,
+        I::IntoIter: Send,
+    {
+        let inputs = Mutex::new(inputs.into_iter().enumerate());
+        // Index of the first query that matched the predicate or failed, after which no more
+        // queries need to run
+        let first_stop = AtomicUsize::new(usize::MAX);
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads {
+                let sender = sender.clone();
+                let (inputs, first_stop) = (&inputs, &first_stop);
+                scope.spawn(move || loop {
+                    let (index, input) = match inputs.lock().unwrap().next() {
+                        Some(next) => next,
+                        None => break,
+                    };
+                    if index > first_stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+
+                    let result = match self.query(index, &input, first_stop) {
+                        Some(result) => result,
+                        None => break,
+                    };
+                    let stop = match &result {
+                        Ok(output) => match &self.stop {
+                            Some(predicate) => predicate(&input, output),
+                            None => false,
+                        },
+                        Err(_) => true,
+                    };
+                    if stop {
+                        first_stop.fetch_min(index, Ordering::SeqCst);
+                    }
+                    let _ = sender.send((index, result));
+                });
+            }
+        });
+        drop(sender);
+
+        let first_stop = first_stop.into_inner();
+        let mut results = receiver
+            .into_iter()
+            .filter(|&(index, _)| index <= first_stop)
+            .collect::<Vec<_>>();
+        results.sort_by_key(|&(index, _)| index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Run the query with the given index, or return `None` if it was cancelled because an
+    /// earlier query stopped the batch.
+    fn query(
+        &self,
+        index: usize,
+        input: &[i64],
+        first_stop: &AtomicUsize,
+    ) -> Option<Result<Vec<i64>, BatchError>> {
+        let mut computer = self.program.clone();
+        match &self.prepare {
+            Some(prepare) => prepare(&mut computer, input),
+            None => computer.provide_input(input.iter().copied()),
+        }
+
+        let mut output = Vec::new();
+        let mut executed = 0;
+        loop {
+            if index > first_stop.load(Ordering::SeqCst) {
+                return None;
+            }
+
+            let budget = match self.max_steps {
+                Some(max) if executed >= max => return Some(Err(BatchError::StepLimit(index))),
+                Some(max) => SLICE.min(max - executed),
+                None => SLICE,
+            };
+            let start = computer.instruction_count();
+            let action = computer.try_run_with_budget(budget);
+            executed += computer.instruction_count() - start;
+
+            match action {
+                Ok(None) => {}
+                Ok(Some(Action::Output(value))) => output.push(value),
+                Ok(Some(Action::NeedsInput)) => return Some(Err(BatchError::NeedsInput(index))),
+                Ok(Some(Action::Halt)) => {
+                    if let Some(finish) = &self.finish {
+                        finish(&computer, &mut output);
+                    }
+                    return Some(Ok(output));
+                }
+                Err(error) => return Some(Err(BatchError::Fault(index, error))),
+            }
+        }
+    }
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchError::NeedsInput(index) => write!(f, "query {} needs more input", index),
+            BatchError::Fault(index, error) => write!(f, "query {}: {}", index, error),
+            BatchError::StepLimit(index) => {
+                write!(f, "query {} executed too many instructions", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::program;
+
+    /// Outputs the product of its two inputs.
+    const MUL: &str = "
+            in [a]
+            in [b]
+            mul [a], [b], [a]
+            out [a]
+            hlt
+        a:  data 0
+        b:  data 0
+    ";
+
+    #[test]
+    fn ordered_outputs() {
+        let mut batch = Batch::new(&program(MUL));
+        batch.set_threads(4);
+        let inputs = (0..100).map(|i| vec![i, i + 1]);
+        let outputs = batch.run(inputs).unwrap();
+        assert_eq!(outputs.len(), 100);
+        assert!(outputs
+            .iter()
+            .enumerate()
+            .all(|(i, output)| output == &[i as i64 * (i as i64 + 1)]));
+
+        assert_eq!(
+            batch.run(vec![vec![2, 3], vec![4]]),
+            Err(BatchError::NeedsInput(1))
+        );
+    }
+
+    #[test]
+    fn early_exit() {
+        // Search for a pair whose product is 56, as in day 2
+        let mut batch = Batch::new(&program(MUL));
+        batch.set_stop(|_, output| output == [56]);
+        let inputs = (0..10).flat_map(|a| (0..10).map(move |b| vec![a, b]));
+        let outputs = batch.run(inputs).unwrap();
+        assert_eq!(outputs.len(), 79);
+        assert_eq!(outputs.last(), Some(&vec![56]));
+    }
+
+    #[test]
+    fn cancel_running_queries() {
+        // Every query but the first runs forever, until it is cancelled
+        let mut batch = Batch::new(&program("in [0]\nout #0\nloop: jnz [0], #loop\nhlt"));
+        batch.set_threads(4);
+        batch.set_stop(|_, _| true);
+        let inputs = (0..8).map(|i| vec![i]);
+        assert_eq!(batch.run(inputs), Ok(vec![vec![0]]));
+    }
+
+    #[test]
+    fn step_limit() {
+        let mut batch = Batch::new(&program("in [0]\nloop: jnz [0], #loop\nhlt"));
+        batch.set_max_steps(Some(100_000));
+        assert_eq!(batch.run(vec![vec![0]]), Ok(vec![vec![]]));
+        assert_eq!(
+            batch.run(vec![vec![0], vec![1]]),
+            Err(BatchError::StepLimit(1))
+        );
+    }
+
+    #[test]
+    fn memory_queries() {
+        // Adds the values at addresses 1 and 2, like the day 2 noun and verb
+        let program = Computer::new(vec![1, 0, 0, 0, 99]);
+        let mut batch = Batch::new(&program);
+        batch.set_prepare(|computer, input| {
+            computer.poke(1, input[0]).unwrap();
+            computer.poke(2, input[1]).unwrap();
+        });
+        batch.set_finish(|computer, output| output.push(computer.peek(0)));
+        let outputs = batch.run(vec![vec![0, 4], vec![4, 4]]).unwrap();
+        assert_eq!(outputs, vec![vec![100], vec![198]]);
+    }
+}
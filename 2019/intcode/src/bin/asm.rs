@@ -0,0 +1,36 @@
+use intcode::asm;
+use std::env;
+use std::fs;
+use std::process;
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: intcode-asm <source>");
+            process::exit(1);
+        }
+    };
+
+    let source = match fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("Failed to read {}: {}", path, error);
+            process::exit(1);
+        }
+    };
+
+    match asm::assemble(&source) {
+        Ok(code) => {
+            let words = code
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>();
+            println!("{}", words.join(","));
+        }
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use intcode::{compile, Computer};
+use std::env;
+use std::process;
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let (path, name) = match &args[..] {
+        [path, name] => (path, name),
+        _ => {
+            eprintln!("usage: intcode-compile <program> <type name>");
+            process::exit(1);
+        }
+    };
+
+    let computer = match Computer::load(path) {
+        Ok(computer) => computer,
+        Err(error) => {
+            eprintln!("Failed to load {}: {}", path, error);
+            process::exit(1);
+        }
+    };
+
+    print!(
+        "{}",
+        compile::compile(&computer.memory().dense_to_vec(), name)
+    );
+}
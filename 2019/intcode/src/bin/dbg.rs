@@ -0,0 +1,419 @@
+use intcode::disasm::Line;
+use intcode::watch::{self, Hit, Watch, WatchId};
+use intcode::{Action, Computer};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+const HELP: &str = "\
+commands:
+  s, step [n]               execute n instructions (default 1)
+  c, continue               run until a breakpoint, input is needed or the program halts
+  bs, back [n]              undo n instructions (default 1)
+  rc, rcontinue             run backwards until a breakpoint or the start of history
+  b, break <address>        break before executing the instruction at an address
+  b, break op <opcode>      break before executing an opcode (number or mnemonic)
+  d, delete [<address> | op <opcode>]
+                            remove a breakpoint, or all breakpoints
+  w, watch [read | write | change] <address> [count]
+                            stop after memory is accessed (default: write)
+  unwatch [id]              remove a watchpoint, or all watchpoints
+  breakpoints               list all breakpoints and watchpoints
+  x, mem <address> [count]  print memory
+  poke <address> <value>    write a value to memory
+  l, list [address] [count] disassemble instructions (default: at the instruction pointer)
+  r, regs                   print the instruction pointer and relative base
+  i, input <value>...       provide integer input
+  t, text <line>            provide a line of ASCII input
+  ascii                     toggle printing output as ASCII
+  save <path>               save a snapshot of the computer
+  restore <path>            restore a snapshot of the computer
+  q, quit                   exit the debugger
+an empty line repeats the previous command";
+
+struct Debugger {
+    computer: Computer,
+    addresses: BTreeSet<usize>,
+    opcodes: BTreeSet<i64>,
+    watches: BTreeMap<WatchId, String>,
+    ascii: bool,
+}
+
+enum Stop {
+    Stepped,
+    Breakpoint,
+    Watchpoint,
+    NeedsInput,
+    Halt,
+    Error,
+}
+
+fn main() {
+    let path = env::args().nth(1).unwrap_or_else(|| "input".to_owned());
+
+    let mut computer = match Computer::load(&path) {
+        Ok(computer) => computer,
+        Err(error) => {
+            eprintln!("Failed to load {}: {}", path, error);
+            process::exit(1);
+        }
+    };
+
+    computer.set_history(true);
+
+    let mut debugger = Debugger {
+        computer,
+        addresses: BTreeSet::new(),
+        opcodes: BTreeSet::new(),
+        watches: BTreeMap::new(),
+        ascii: false,
+    };
+
+    debugger.show_current();
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut previous = String::new();
+
+    loop {
+        print!("(dbg) ");
+        io::stdout().flush().unwrap();
+
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+
+        let line = if line.trim().is_empty() {
+            previous.clone()
+        } else {
+            line
+        };
+
+        match debugger.execute(&line) {
+            Ok(true) => break,
+            Ok(false) => {}
+            Err(message) => println!("error: {}", message),
+        }
+
+        previous = line;
+    }
+}
+
+impl Debugger {
+    /// Execute a command, returning `true` if the debugger should exit.
+    fn execute(&mut self, line: &str) -> Result<bool, String> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => return Ok(false),
+        };
+        let args = words.collect::<Vec<_>>();
+
+        match command {
+            "s" | "step" => {
+                let count = args.first().map_or(Ok(1), |arg| parse(arg))?;
+                for _ in 0..count {
+                    match self.step() {
+                        Stop::Stepped => {}
+                        stop => {
+                            self.report(stop);
+                            break;
+                        }
+                    }
+                }
+                self.show_current();
+            }
+            "c" | "continue" => {
+                let stop = self.resume();
+                self.report(stop);
+                self.show_current();
+            }
+            "bs" | "back" => {
+                let count = args.first().map_or(Ok(1), |arg| parse(arg))?;
+                for _ in 0..count {
+                    if !self.computer.step_back() {
+                        println!("start of history");
+                        break;
+                    }
+                }
+                self.show_current();
+            }
+            "rc" | "rcontinue" => {
+                loop {
+                    if !self.computer.step_back() {
+                        println!("start of history");
+                        break;
+                    }
+                    if self.at_breakpoint() {
+                        println!("breakpoint");
+                        break;
+                    }
+                }
+                self.show_current();
+            }
+            "b" | "break" => match args.as_slice() {
+                ["op", opcode] => {
+                    self.opcodes.insert(parse_opcode(opcode)?);
+                }
+                [address] => {
+                    self.addresses.insert(parse(address)?);
+                }
+                _ => return Err("usage: break <address> | break op <opcode>".into()),
+            },
+            "d" | "delete" => match args.as_slice() {
+                [] => {
+                    self.addresses.clear();
+                    self.opcodes.clear();
+                }
+                ["op", opcode] => {
+                    self.opcodes.remove(&parse_opcode(opcode)?);
+                }
+                [address] => {
+                    self.addresses.remove(&parse(address)?);
+                }
+                _ => return Err("usage: delete [<address> | op <opcode>]".into()),
+            },
+            "breakpoints" => {
+                for address in &self.addresses {
+                    println!("address {}", address);
+                }
+                for opcode in &self.opcodes {
+                    println!("opcode {}", opcode);
+                }
+                for (id, description) in &self.watches {
+                    println!("watch {}: {}", id, description);
+                }
+            }
+            "w" | "watch" => {
+                let (kind, args) = match args.split_first() {
+                    Some((&kind, rest)) if ["read", "write", "change"].contains(&kind) => {
+                        (kind, rest)
+                    }
+                    _ => ("write", &args[..]),
+                };
+                let (start, count): (usize, usize) = match args {
+                    [address] => (parse(address)?, 1),
+                    [address, count] => (parse(address)?, parse(count)?),
+                    _ => {
+                        return Err("usage: watch [read | write | change] <address> [count]".into())
+                    }
+                };
+                let watch = match kind {
+                    "read" => Watch::Read,
+                    "write" => Watch::Write,
+                    _ => Watch::change(|_, _| true),
+                };
+                let end = start
+                    .checked_add(count.max(1) - 1)
+                    .ok_or("watched addresses are out of range")?;
+                let id = self.computer.add_watchpoint(start..=end, watch);
+                let description = format!("{} {}..={}", kind, start, end);
+                println!("watch {}: {}", id, description);
+                self.watches.insert(id, description);
+            }
+            "unwatch" => match args.as_slice() {
+                [] => {
+                    self.computer.clear_watchpoints();
+                    self.watches.clear();
+                }
+                [id] => {
+                    let id = *self
+                        .watches
+                        .keys()
+                        .find(|watch| watch.to_string() == *id)
+                        .ok_or_else(|| format!("no watchpoint {}", id))?;
+                    self.computer.remove_watchpoint(id);
+                    self.watches.remove(&id);
+                }
+                _ => return Err("usage: unwatch [id]".into()),
+            },
+            "x" | "mem" => {
+                let start: usize = parse(args.first().ok_or("usage: mem <address> [count]")?)?;
+                let count: usize = args.get(1).map_or(Ok(1), |arg| parse(arg))?;
+                let end = start
+                    .checked_add(count)
+                    .ok_or("addresses are out of range")?;
+                for row in (start..end).step_by(8) {
+                    let end = row.saturating_add(8).min(end);
+                    let values = (row..end)
+                        .map(|address| self.computer.peek(address).to_string())
+                        .collect::<Vec<_>>();
+                    println!("{:>6}: {}", row, values.join(", "));
+                }
+            }
+            "poke" => match args.as_slice() {
+                [address, value] => self
+                    .computer
+                    .poke(parse(address)?, parse(value)?)
+                    .map_err(|fault| fault.to_string())?,
+                _ => return Err("usage: poke <address> <value>".into()),
+            },
+            "l" | "list" => {
+                let ip = self.computer.instruction_pointer();
+                let start = args.first().map_or(Ok(ip), |arg| parse(arg))?;
+                let count = args.get(1).map_or(Ok(10), |arg| parse(arg))?;
+                self.list(start, count);
+            }
+            "r" | "regs" => {
+                println!("ip: {}", self.computer.instruction_pointer());
+                println!("rb: {}", self.computer.relative_base());
+                println!("pending input: {}", self.computer.pending_input());
+                println!("halted: {}", self.computer.is_halted());
+                println!("instructions: {}", self.computer.instruction_count());
+            }
+            "i" | "input" => {
+                let values = args
+                    .iter()
+                    .map(|arg| parse(arg))
+                    .collect::<Result<Vec<i64>, _>>()?;
+                self.computer.provide_input(values);
+            }
+            "t" | "text" => {
+                let text = line.trim_start()[command.len()..].trim_start();
+                let values = text.chars().chain(Some('\n')).map(|ch| ch as u8 as i64);
+                self.computer.provide_input(values);
+            }
+            "ascii" => {
+                self.ascii = !self.ascii;
+                println!("ascii output: {}", if self.ascii { "on" } else { "off" });
+            }
+            "save" => match args.as_slice() {
+                [path] => self
+                    .computer
+                    .save_snapshot(path)
+                    .map_err(|error| error.to_string())?,
+                _ => return Err("usage: save <path>".into()),
+            },
+            "restore" => match args.as_slice() {
+                [path] => {
+                    self.computer =
+                        Computer::load_snapshot(path).map_err(|error| error.to_string())?;
+                    self.computer.set_history(true);
+                    self.show_current();
+                }
+                _ => return Err("usage: restore <path>".into()),
+            },
+            "h" | "help" => println!("{}", HELP),
+            "q" | "quit" => return Ok(true),
+            _ => return Err(format!("unknown command `{}`, try `help`", command)),
+        }
+
+        Ok(false)
+    }
+
+    fn step(&mut self) -> Stop {
+        match self.computer.step_watched() {
+            Ok(None) => Stop::Stepped,
+            Ok(Some(watch::Stop::Action(Action::Output(value)))) => {
+                self.print_output(value);
+                Stop::Stepped
+            }
+            Ok(Some(watch::Stop::Action(Action::NeedsInput))) => Stop::NeedsInput,
+            Ok(Some(watch::Stop::Action(Action::Halt))) => Stop::Halt,
+            Ok(Some(watch::Stop::Watchpoint(Hit { address, old, new }))) => {
+                println!("watchpoint: [{}] {} -> {}", address, old, new);
+                Stop::Watchpoint
+            }
+            Err(error) => {
+                println!("fault: {}", error);
+                Stop::Error
+            }
+        }
+    }
+
+    fn resume(&mut self) -> Stop {
+        // Always execute at least one instruction so we can continue from a breakpoint
+        match self.step() {
+            Stop::Stepped => {}
+            stop => return stop,
+        }
+
+        loop {
+            if self.at_breakpoint() {
+                return Stop::Breakpoint;
+            }
+
+            match self.step() {
+                Stop::Stepped => {}
+                stop => return stop,
+            }
+        }
+    }
+
+    fn at_breakpoint(&self) -> bool {
+        let ip = self.computer.instruction_pointer();
+        self.addresses.contains(&ip) || self.opcodes.contains(&(self.computer.peek(ip) % 100))
+    }
+
+    fn report(&self, stop: Stop) {
+        match stop {
+            Stop::Stepped | Stop::Watchpoint | Stop::Error => {}
+            Stop::Breakpoint => println!("breakpoint"),
+            Stop::NeedsInput => println!("waiting for input"),
+            Stop::Halt => println!("halted"),
+        }
+    }
+
+    fn print_output(&self, value: i64) {
+        if self.ascii && (0..128).contains(&value) {
+            print!("{}", value as u8 as char);
+            io::stdout().flush().unwrap();
+        } else {
+            println!("output: {}", value);
+        }
+    }
+
+    fn show_current(&self) {
+        self.list(self.computer.instruction_pointer(), 1);
+    }
+
+    fn list(&self, start: usize, count: usize) {
+        let mut address = start;
+        for _ in 0..count {
+            let line = match self.computer.instruction_at(address) {
+                Ok(instruction) => Line::Instruction {
+                    address,
+                    instruction,
+                },
+                Err(_) => Line::Data {
+                    address,
+                    values: vec![self.computer.peek(address)],
+                },
+            };
+
+            let marker = if address == self.computer.instruction_pointer() {
+                "=>"
+            } else {
+                "  "
+            };
+            println!("{}{}", marker, line);
+
+            let size = match line {
+                Line::Instruction { instruction, .. } => instruction.size(),
+                Line::Data { .. } => 1,
+            };
+            address = match address.checked_add(size) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    }
+}
+
+fn parse<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("invalid number `{}`", text))
+}
+
+/// Parse an opcode, using the disassembler's mnemonic for it if it is not a number.
+fn parse_opcode(text: &str) -> Result<i64, String> {
+    let opcode = (1..=9).chain(Some(99)).find(|&opcode| {
+        let computer = Computer::new(vec![opcode, 0, 0, 0]);
+        computer
+            .instruction_at(0)
+            .is_ok_and(|instruction| instruction.mnemonic() == text)
+    });
+    opcode.map_or_else(|| parse(text), Ok)
+}
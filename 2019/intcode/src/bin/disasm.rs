@@ -0,0 +1,28 @@
+use intcode::Computer;
+use std::env;
+use std::process;
+
+fn main() {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let dot = args.iter().any(|arg| arg == "--dot");
+    args.retain(|arg| arg != "--dot");
+    let path = args.pop().unwrap_or_else(|| "input".to_owned());
+
+    let computer = match Computer::load(&path) {
+        Ok(computer) => computer,
+        Err(error) => {
+            eprintln!("Failed to load {}: {}", path, error);
+            process::exit(1);
+        }
+    };
+
+    if dot {
+        let analysis = computer.analyze();
+        for warning in &analysis.warnings {
+            eprintln!("warning: {}", warning);
+        }
+        print!("{}", analysis.to_dot());
+    } else {
+        print!("{}", computer.disassemble());
+    }
+}
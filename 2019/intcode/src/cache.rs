@@ -0,0 +1,53 @@
+//! A cache of decoded instructions, so that loops do not have to decode the same instructions
+//! over and over again.
+//!
+//! Only the dense region of memory is cached, so jumping far past the program does not grow the
+//! cache. Clones start with an empty cache instead of copying it, which keeps cloning a computer
+//! for a short query cheap.
+
+use crate::memory::DENSE_SIZE;
+use crate::Instruction;
+
+/// Size of the largest instruction, in cells.
+const MAX_SIZE: usize = 4;
+
+#[derive(Debug, Default)]
+pub(crate) struct DecodeCache {
+    instructions: Vec<Option<Instruction>>,
+}
+
+impl DecodeCache {
+    pub fn get(&self, address: usize) -> Option<Instruction> {
+        self.instructions.get(address).copied().flatten()
+    }
+
+    pub fn insert(&mut self, address: usize, instruction: Instruction) {
+        if address >= DENSE_SIZE {
+            return;
+        }
+        if address >= self.instructions.len() {
+            self.instructions.resize(address + 1, None);
+        }
+        self.instructions[address] = Some(instruction);
+    }
+
+    /// Forget every instruction that overlaps with a memory address.
+    pub fn invalidate(&mut self, address: usize) {
+        if address >= self.instructions.len() {
+            return;
+        }
+        for index in address.saturating_sub(MAX_SIZE - 1)..=address {
+            if let Some(instruction) = self.instructions[index] {
+                if index + instruction.size() > address {
+                    self.instructions[index] = None;
+                }
+            }
+        }
+    }
+}
+
+impl Clone for DecodeCache {
+    fn clone(&self) -> DecodeCache {
+        DecodeCache::default()
+    }
+}
@@ -0,0 +1,377 @@
+//! Translating programs into Rust source, so that they can be compiled along with the rest of a
+//! puzzle solution.
+//!
+//! The generated source defines a type that wraps a [`Computer`] and runs the program natively.
+//! Every instruction that can be found statically becomes an arm in a jump table. Whenever the
+//! program jumps somewhere that is not in the table, or does something that would fault, the
+//! wrapped interpreter takes over until the next action. Writes that change the program's own
+//! instructions are tracked, and once that happens every instruction is checked against memory
+//! before it runs, so self-modifying programs behave exactly as they would in the interpreter.
+//! Computers that are profiling, recording history or a transcript, not using wrapping arithmetic
+//! or restricted to an earlier instruction set are always interpreted.
+//!
+//! From a `build.rs`:
+//!
+//! ```ignore
+//! fn main() {
+//!     let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("program.rs");
+//!     intcode::compile::compile_file("input", "Program", out).unwrap();
+//!     println!("cargo:rerun-if-changed=input");
+//! }
+//! ```
+//!
+//! And then in the puzzle binary:
+//!
+//! ```ignore
+//! include!(concat!(env!("OUT_DIR"), "/program.rs"));
+//!
+//! let mut program = Program::new(Computer::load("input")?);
+//! let output = program.run();
+//! ```
+
+use crate::analysis::discover;
+use crate::{Address, Computer, Instruction, Parameter, Result};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Generate the source for a type called `name` that runs `code`.
+pub fn compile(code: &[i64], name: &str) -> String {
+    let instructions = discover(code);
+
+    let mut is_code = vec![false; code.len()];
+    for (&address, instruction) in &instructions {
+        for cell in &mut is_code[address..address + instruction.size()] {
+            *cell = true;
+        }
+    }
+
+    let generator = Generator { code, is_code };
+    let mut out = String::new();
+    generator.header(&mut out, name);
+    for (&address, &instruction) in &instructions {
+        generator.instruction(&mut out, address, instruction);
+    }
+    generator.footer(&mut out);
+    out
+}
+
+/// Compile the program stored at `input`, in the same format accepted by [`Computer::load`], and
+/// write the generated source to `output`.
+pub fn compile_file(input: impl AsRef<Path>, name: &str, output: impl AsRef<Path>) -> Result<()> {
+    let computer = Computer::load(input)?;
+    fs::write(output, compile(&computer.memory().dense_to_vec(), name))?;
+    Ok(())
+}
+
+struct Generator<'a> {
+    code: &'a [i64],
+    is_code: Vec<bool>,
+}
+
+impl Generator<'_> {
+    fn header(&self, out: &mut String, name: &str) {
+        let _ = write!(
+            out,
+            "\
+// Generated by intcode::compile. Do not edit.
+
+pub struct {name} {{
+    computer: ::intcode::Computer,
+    dirty: bool,
+}}
+
+#[allow(clippy::all, unreachable_code, unused_mut, unused_variables)]
+impl {name} {{
+    pub fn new(computer: ::intcode::Computer) -> {name} {{
+        let (image, code) = {name}::image();
+        let dirty = (0..image.len()).any(|i| code[i] && computer.peek(i) != image[i]);
+        {name} {{ computer, dirty }}
+    }}
+
+    pub fn computer(&self) -> &::intcode::Computer {{
+        &self.computer
+    }}
+
+    pub fn into_inner(self) -> ::intcode::Computer {{
+        self.computer
+    }}
+
+    pub fn provide_input(&mut self, input: impl IntoIterator<Item = i64>) {{
+        self.computer.provide_input(input);
+    }}
+
+    pub fn run(&mut self) -> ::intcode::Action {{
+        match self.try_run() {{
+            Ok(action) => action,
+            Err(error) => panic!(\"{{}}\", error),
+        }}
+    }}
+
+    pub fn try_run(&mut self) -> Result<::intcode::Action, ::intcode::IntcodeError> {{
+        use ::intcode::compile::runtime;
+        use ::intcode::Action;
+
+        let (image, code) = {name}::image();
+        let c = &mut self.computer;
+        let dirty = &mut self.dirty;
+        if c.is_halted() || runtime::observed(c) {{
+            return c.try_run();
+        }}
+
+        let mut ip = c.instruction_pointer();
+        let mut rb = c.relative_base();
+        let mut executed = 0u64;
+
+        loop {{
+            match ip {{
+",
+            name = name
+        );
+    }
+
+    fn footer(&self, out: &mut String) {
+        let _ = write!(
+            out,
+            "\
+                _ => break,
+            }}
+        }}
+
+        runtime::sync(c, ip, rb, executed);
+        c.try_run()
+    }}
+
+    fn image() -> (&'static [i64], &'static [bool]) {{
+        static IMAGE: [i64; {len}] = {image:?};
+        static CODE: [bool; {len}] = {code:?};
+        (&IMAGE, &CODE)
+    }}
+}}
+",
+            len = self.code.len(),
+            image = self.code,
+            code = self.is_code,
+        );
+    }
+
+    fn instruction(&self, out: &mut String, address: usize, instruction: Instruction) {
+        let size = instruction.size();
+        let next = address + size;
+        let mut body = Vec::new();
+
+        let words = &self.code[address..next];
+        let guard = words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| format!("c.peek({}) != {}", address + i, word))
+            .collect::<Vec<_>>();
+        body.push(format!(
+            "if *dirty && ({}) {{ break; }}",
+            guard.join(" || ")
+        ));
+
+        let complete = match instruction {
+            Instruction::Halt => {
+                body.push("break;".to_owned());
+                false
+            }
+            Instruction::Add(a, b, target) => {
+                self.binary(&mut body, address, a, b, target, "a.wrapping_add(b)")
+            }
+            Instruction::Mul(a, b, target) => {
+                self.binary(&mut body, address, a, b, target, "a.wrapping_mul(b)")
+            }
+            Instruction::Slt(a, b, target) => {
+                self.binary(&mut body, address, a, b, target, "(a < b) as i64")
+            }
+            Instruction::Seq(a, b, target) => {
+                self.binary(&mut body, address, a, b, target, "(a == b) as i64")
+            }
+            Instruction::Input(target) => {
+                // The input is only consumed once it has been written, like in the interpreter
+                if self.index(&mut body, "i", target) {
+                    body.push(format!(
+                        "let v = match runtime::next_input(c) {{ Some(v) => v, None => {{ \
+                         runtime::sync(c, {}, rb, executed); return Ok(Action::NeedsInput); }} }};",
+                        address
+                    ));
+                    self.write(&mut body, address, target);
+                    body.push("runtime::take_input(c);".to_owned());
+                    true
+                } else {
+                    false
+                }
+            }
+            Instruction::Output(a) => {
+                if self.read(&mut body, "v", a) {
+                    body.push(format!(
+                        "runtime::sync(c, {}, rb, executed + 1); return Ok(Action::Output(v));",
+                        next
+                    ));
+                }
+                false
+            }
+            Instruction::Jnz(a, target) | Instruction::Jez(a, target) => {
+                let comparison = match instruction {
+                    Instruction::Jnz(..) => "!=",
+                    _ => "==",
+                };
+                if self.read(&mut body, "a", a) {
+                    let mut jump = Vec::new();
+                    if self.read(&mut jump, "t", target) {
+                        jump.push("if t < 0 { break; }".to_owned());
+                        jump.push("ip = t as usize;".to_owned());
+                    }
+                    body.push(format!(
+                        "if a {} 0 {{ {} }} else {{ ip = {}; }}",
+                        comparison,
+                        jump.join(" "),
+                        next
+                    ));
+                    body.push("executed += 1;".to_owned());
+                }
+                false
+            }
+            Instruction::Reb(a) => {
+                if self.read(&mut body, "a", a) {
+                    body.push("rb = rb.wrapping_add(a);".to_owned());
+                    true
+                } else {
+                    false
+                }
+            }
+        };
+
+        if complete {
+            body.push(format!("ip = {};", next));
+            body.push("executed += 1;".to_owned());
+        }
+
+        let _ = writeln!(out, "                {} => {{", address);
+        for line in body {
+            let _ = writeln!(out, "                    {}", line);
+        }
+        let _ = writeln!(out, "                }}");
+    }
+
+    /// Emit an instruction that combines two parameters and writes the result. Returns whether the
+    /// instruction completes normally.
+    fn binary(
+        &self,
+        body: &mut Vec<String>,
+        address: usize,
+        a: Parameter,
+        b: Parameter,
+        target: Address,
+        expression: &str,
+    ) -> bool {
+        if !self.read(body, "a", a) || !self.read(body, "b", b) {
+            return false;
+        }
+        body.push(format!("let v = {};", expression));
+        if !self.index(body, "i", target) {
+            return false;
+        }
+        self.write(body, address, target);
+        true
+    }
+
+    /// Emit code that reads a parameter into `name`. Returns false if the read always faults, in
+    /// which case the interpreter is left to report it.
+    fn read(&self, body: &mut Vec<String>, name: &str, parameter: Parameter) -> bool {
+        match parameter {
+            Parameter::Value(value) => {
+                body.push(format!("let {}: i64 = {};", name, value));
+                true
+            }
+            Parameter::Address(address) => {
+                if !self.index(body, name, address) {
+                    return false;
+                }
+                body.push(format!("let {0} = c.peek({0});", name));
+                true
+            }
+        }
+    }
+
+    /// Emit code that computes the index of `address` into `name`.
+    fn index(&self, body: &mut Vec<String>, name: &str, address: Address) -> bool {
+        match address {
+            Address::Position(index) if index < 0 => {
+                body.push("break;".to_owned());
+                false
+            }
+            Address::Position(index) => {
+                body.push(format!("let {}: usize = {};", name, index));
+                true
+            }
+            Address::Relative(offset) => {
+                body.push(format!(
+                    "let {0} = match rb.checked_add({1}) {{ Some({0}) if {0} >= 0 => {0} as usize, _ => break }};",
+                    name, offset
+                ));
+                true
+            }
+        }
+    }
+
+    /// Emit code that writes `v` to the index `i`, keeping track of writes that change the program.
+    fn write(&self, body: &mut Vec<String>, address: usize, target: Address) {
+        body.push(format!(
+            "if let Err(fault) = c.poke(i, v) {{ return Err(runtime::fault(c, {}, rb, executed, fault)); }}",
+            address
+        ));
+        match target {
+            Address::Position(index) => {
+                let index = index as usize;
+                if index < self.code.len() && self.is_code[index] {
+                    body.push(format!("if v != {} {{ *dirty = true; }}", self.code[index]));
+                }
+            }
+            Address::Relative(_) => {
+                body.push(
+                    "if i < code.len() && code[i] && v != image[i] { *dirty = true; }".to_owned(),
+                );
+            }
+        }
+    }
+}
+
+/// Support functions used by generated code.
+#[doc(hidden)]
+pub mod runtime {
+    use crate::{Computer, Fault, IntcodeError};
+
+    pub fn sync(computer: &mut Computer, ip: usize, rb: i64, executed: u64) {
+        computer.sync(ip, rb, executed);
+    }
+
+    /// Whether the computer does anything while running that only the interpreter supports.
+    pub fn observed(computer: &Computer) -> bool {
+        computer.interpreted_only()
+    }
+
+    pub fn next_input(computer: &Computer) -> Option<i64> {
+        computer.next_input()
+    }
+
+    pub fn take_input(computer: &mut Computer) -> Option<i64> {
+        computer.take_input()
+    }
+
+    pub fn fault(
+        computer: &mut Computer,
+        instruction: usize,
+        rb: i64,
+        executed: u64,
+        fault: Fault,
+    ) -> IntcodeError {
+        sync(computer, instruction, rb, executed);
+        IntcodeError {
+            fault,
+            instruction,
+            opcode: computer.peek(instruction),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{Address, Computer, Instruction, Parameter};
+use std::fmt;
+
+/// Maximum number of values to print on a single `data` line.
+const DATA_WIDTH: usize = 8;
+
+pub struct Listing {
+    pub lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Line {
+    Instruction {
+        address: usize,
+        instruction: Instruction,
+    },
+    Data {
+        address: usize,
+        values: Vec<i64>,
+    },
+}
+
+/// Disassemble a program by sweeping linearly over its memory. Any values that do not decode to
+/// a valid instruction (or would not encode back into the same values) are emitted as data.
+pub fn disassemble(code: &[i64]) -> Listing {
+    let mut lines = Vec::new();
+    let mut data = Vec::new();
+    let mut data_start = 0;
+    let mut address = 0;
+
+    while address < code.len() {
+        match decode_at(code, address) {
+            Some(instruction) => {
+                flush_data(&mut lines, &mut data, data_start);
+                lines.push(Line::Instruction {
+                    address,
+                    instruction,
+                });
+                address += instruction.size();
+            }
+            None => {
+                if data.is_empty() {
+                    data_start = address;
+                }
+                data.push(code[address]);
+                address += 1;
+
+                if data.len() == DATA_WIDTH {
+                    flush_data(&mut lines, &mut data, data_start);
+                }
+            }
+        }
+    }
+
+    flush_data(&mut lines, &mut data, data_start);
+
+    Listing { lines }
+}
+
+fn flush_data(lines: &mut Vec<Line>, data: &mut Vec<i64>, address: usize) {
+    if !data.is_empty() {
+        lines.push(Line::Data {
+            address,
+            values: std::mem::take(data),
+        });
+    }
+}
+
+/// The instruction at `address`, if it fits in `code` and encodes back to the same values.
+pub(crate) fn decode_at(code: &[i64], address: usize) -> Option<Instruction> {
+    let mut words = code[address..].iter().copied();
+    let instruction = Instruction::decode(|| Ok(words.next().unwrap_or(0))).ok()?;
+
+    let size = instruction.size();
+    let fits = address + size <= code.len();
+    if fits && instruction.encode() == code[address..address + size] {
+        Some(instruction)
+    } else {
+        None
+    }
+}
+
+impl Computer {
+    pub fn disassemble(&self) -> Listing {
+        disassemble(&self.memory.dense_to_vec())
+    }
+}
+
+impl Instruction {
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Instruction::Halt => "hlt",
+            Instruction::Add(_, _, _) => "add",
+            Instruction::Mul(_, _, _) => "mul",
+            Instruction::Input(_) => "in",
+            Instruction::Output(_) => "out",
+            Instruction::Jnz(_, _) => "jnz",
+            Instruction::Jez(_, _) => "jez",
+            Instruction::Slt(_, _, _) => "slt",
+            Instruction::Seq(_, _, _) => "seq",
+            Instruction::Reb(_) => "rb",
+        }
+    }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Line::Instruction {
+                address,
+                instruction,
+            } => write!(f, "{:>6}: {}", address, instruction),
+            Line::Data { address, values } => {
+                write!(f, "{:>6}: data ", address)?;
+                for (i, value) in values.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.mnemonic())?;
+        for (i, parameter) in self.parameters().iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(f, "{}{}", separator, parameter)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Parameter::Address(address) => write!(f, "{}", address),
+            Parameter::Value(value) => write!(f, "#{}", value),
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Address::Position(index) => write!(f, "[{}]", index),
+            Address::Relative(offset) => write!(f, "rb[{}]", offset),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listing() {
+        let code = vec![109, 1, 21101, 12, -3, 0, 4, 7, 99, 5, 10];
+        let listing = disassemble(&code).to_string();
+        assert_eq!(
+            listing,
+            "     0: rb #1\n     \
+                  2: add #12, #-3, rb[0]\n     \
+                  6: out [7]\n     \
+                  8: hlt\n     \
+                  9: data 5, 10\n"
+        );
+    }
+
+    #[test]
+    fn non_canonical_is_data() {
+        // `hlt` with a parameter mode is not something we could assemble back
+        let code = vec![199, 1001, 0, 1];
+        let listing = disassemble(&code);
+        assert_eq!(
+            listing.lines,
+            vec![Line::Data {
+                address: 0,
+                values: code,
+            }]
+        );
+    }
+}
@@ -0,0 +1,424 @@
+//! Searching every state a program can reach from a set of input choices.
+//!
+//! Starting from a computer, every choice is tried by providing its input to a clone, and running
+//! the clone until it needs more input or halts. What it output along the way is given to a
+//! decoder, which either labels the new state or rejects it as a dead end. States with the same
+//! instruction pointer, relative base, memory and pending input are only explored once, so a droid
+//! that walks back to where it came from ends up at a state that has already been seen, as long as
+//! the program does not remember how it got there.
+
+use crate::memory::Memory;
+use crate::{Action, Computer, IntcodeError};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Order {
+    BreadthFirst,
+    DepthFirst,
+}
+
+pub struct Explorer<C> {
+    choices: Vec<(C, Vec<i64>)>,
+    order: Order,
+    max_depth: Option<usize>,
+    max_nodes: Option<usize>,
+    max_steps: Option<u64>,
+}
+
+pub struct Graph<C, O> {
+    pub nodes: Vec<Node<O>>,
+    pub edges: Vec<Edge<C>>,
+}
+
+pub struct Node<O> {
+    /// The computer, waiting for input or halted.
+    pub computer: Computer,
+    /// What the decoder made of the output that led here. `None` for the starting node.
+    pub output: Option<O>,
+    /// Number of choices made to first reach this node. With breadth-first order this is the
+    /// length of the shortest path from the start.
+    pub depth: usize,
+    /// The edge this node was first reached through.
+    pub parent: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge<C> {
+    pub from: usize,
+    pub to: usize,
+    pub choice: C,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExploreError {
+    /// A choice made from the node `from` faulted. `from` is `None` while running the start.
+    Fault {
+        from: Option<usize>,
+        error: IntcodeError,
+    },
+    /// A choice made from the node `from` ran for more instructions than allowed without needing
+    /// input or halting.
+    StepLimit { from: Option<usize> },
+}
+
+impl<C: Clone> Explorer<C> {
+    /// Explore by making any of `choices`, each of which provides its values as input.
+    pub fn new(choices: impl IntoIterator<Item = (C, Vec<i64>)>) -> Explorer<C> {
+        Explorer {
+            choices: choices.into_iter().collect(),
+            order: Order::BreadthFirst,
+            max_depth: None,
+            max_nodes: None,
+            max_steps: None,
+        }
+    }
+
+    pub fn set_order(&mut self, order: Order) {
+        self.order = order;
+    }
+
+    /// Stop making choices from nodes at this depth.
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    /// Stop discovering new nodes once there are this many.
+    pub fn set_max_nodes(&mut self, max_nodes: Option<usize>) {
+        self.max_nodes = max_nodes;
+    }
+
+    /// Fail with [`ExploreError::StepLimit`] if a choice executes this many instructions without
+    /// needing input or halting.
+    pub fn set_max_steps(&mut self, max_steps: Option<u64>) {
+        self.max_steps = max_steps;
+    }
+
+    /// Explore every state reachable from `start`. Whatever `start` outputs before it first needs
+    /// input is ignored. `decode` is called with the output of every choice, and returns `None`
+    /// for choices that should not be explored further.
+    pub fn explore<O>(
+        &self,
+        start: &Computer,
+        mut decode: impl FnMut(&[i64]) -> Option<O>,
+    ) -> Result<Graph<C, O>, ExploreError> {
+        let mut computer = start.clone();
+        self.run_until_input(&mut computer, &mut Vec::new(), None)?;
+
+        // Nodes by the hash of their state. Hashes can collide, so states are compared too
+        let mut seen = HashMap::new();
+        seen.insert(state_hash(&computer), vec![0]);
+
+        let mut graph = Graph {
+            nodes: vec![Node {
+                computer,
+                output: None,
+                depth: 0,
+                parent: None,
+            }],
+            edges: Vec::new(),
+        };
+
+        let mut queue = VecDeque::new();
+        queue.push_back(0);
+
+        let mut outputs = Vec::new();
+        while let Some(from) = match self.order {
+            Order::BreadthFirst => queue.pop_front(),
+            Order::DepthFirst => queue.pop_back(),
+        } {
+            let node = &graph.nodes[from];
+            let depth = node.depth + 1;
+            if node.computer.is_halted() || matches!(self.max_depth, Some(max) if depth > max) {
+                continue;
+            }
+
+            for (choice, input) in &self.choices {
+                let mut computer = graph.nodes[from].computer.clone();
+                computer.provide_input(input.iter().copied());
+                outputs.clear();
+                self.run_until_input(&mut computer, &mut outputs, Some(from))?;
+
+                let output = match decode(&outputs) {
+                    Some(output) => output,
+                    None => continue,
+                };
+
+                let edge = graph.edges.len();
+                let hash = state_hash(&computer);
+                let known = seen.get(&hash).and_then(|nodes: &Vec<usize>| {
+                    let mut nodes = nodes.iter().copied();
+                    nodes.find(|&node| same_state(&graph.nodes[node].computer, &computer))
+                });
+                let to = match known {
+                    Some(to) => to,
+                    None => {
+                        if matches!(self.max_nodes, Some(max) if graph.nodes.len() >= max) {
+                            continue;
+                        }
+                        let to = graph.nodes.len();
+                        seen.entry(hash).or_default().push(to);
+                        graph.nodes.push(Node {
+                            computer,
+                            output: Some(output),
+                            depth,
+                            parent: Some(edge),
+                        });
+                        queue.push_back(to);
+                        to
+                    }
+                };
+
+                graph.edges.push(Edge {
+                    from,
+                    to,
+                    choice: choice.clone(),
+                });
+            }
+        }
+
+        Ok(graph)
+    }
+
+    /// Run until the computer needs input or halts, collecting what it outputs.
+    fn run_until_input(
+        &self,
+        computer: &mut Computer,
+        outputs: &mut Vec<i64>,
+        from: Option<usize>,
+    ) -> Result<(), ExploreError> {
+        let start = computer.instruction_count();
+        loop {
+            let action = match self.max_steps {
+                Some(max) => {
+                    let executed = computer.instruction_count() - start;
+                    computer
+                        .try_run_with_budget(max.saturating_sub(executed))
+                        .transpose()
+                }
+                None => Some(computer.try_run()),
+            };
+            match action {
+                Some(Ok(Action::Output(value))) => outputs.push(value),
+                Some(Ok(_)) => return Ok(()),
+                Some(Err(error)) => return Err(ExploreError::Fault { from, error }),
+                None => return Err(ExploreError::StepLimit { from }),
+            }
+        }
+    }
+}
+
+impl<C, O> Graph<C, O> {
+    /// The first node, in the order they were discovered, whose output matches.
+    pub fn find(&self, mut predicate: impl FnMut(&O) -> bool) -> Option<usize> {
+        self.nodes.iter().position(|node| match &node.output {
+            Some(output) => predicate(output),
+            None => false,
+        })
+    }
+
+    /// The choices that first led from the start to `node`.
+    pub fn path(&self, mut node: usize) -> Vec<&C> {
+        let mut path = Vec::new();
+        while let Some(edge) = self.nodes[node].parent {
+            path.push(&self.edges[edge].choice);
+            node = self.edges[edge].from;
+        }
+        path.reverse();
+        path
+    }
+
+    pub fn edges_from(&self, node: usize) -> impl Iterator<Item = &Edge<C>> {
+        self.edges.iter().filter(move |edge| edge.from == node)
+    }
+}
+
+/// Hash of everything that determines what a computer does next. Dense cells that are zero and
+/// pages of zeros are skipped, so memory that was merely allocated does not make otherwise equal
+/// states differ.
+fn state_hash(computer: &Computer) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write_usize(computer.instruction);
+    hasher.write_i64(computer.relative_base);
+    hasher.write_u8(computer.halted as u8);
+    computer.input.hash(&mut hasher);
+
+    let mut wide = computer.wide.iter().collect::<Vec<_>>();
+    wide.sort_by_key(|&(&address, _)| address);
+    wide.hash(&mut hasher);
+
+    for (address, value) in dense_cells(&computer.memory) {
+        hasher.write_usize(address);
+        hasher.write_i64(value);
+    }
+    for (start, cells) in pages(&computer.memory) {
+        hasher.write_usize(start);
+        for &value in cells {
+            hasher.write_i64(value);
+        }
+    }
+
+    hasher.finish()
+}
+
+/// Whether two computers are in the same state, ignoring memory that was merely allocated like
+/// `state_hash` does.
+fn same_state(a: &Computer, b: &Computer) -> bool {
+    a.instruction == b.instruction
+        && a.relative_base == b.relative_base
+        && a.halted == b.halted
+        && a.input == b.input
+        && a.wide == b.wide
+        && dense_cells(&a.memory).eq(dense_cells(&b.memory))
+        && pages(&a.memory) == pages(&b.memory)
+}
+
+/// The nonzero dense cells with their addresses.
+fn dense_cells(memory: &Memory) -> impl Iterator<Item = (usize, i64)> + '_ {
+    let cells = memory.dense_chunks().flatten().copied().enumerate();
+    cells.filter(|&(_, value)| value != 0)
+}
+
+/// The pages that are not all zeros, by their start address.
+fn pages(memory: &Memory) -> Vec<(usize, &[i64])> {
+    let mut pages = memory
+        .pages()
+        .filter(|(_, cells)| cells.iter().any(|&value| value != 0))
+        .collect::<Vec<_>>();
+    pages.sort_by_key(|&(start, _)| start);
+    pages
+}
+
+impl fmt::Display for ExploreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let from = |f: &mut fmt::Formatter, from: Option<usize>| match from {
+            Some(node) => write!(f, "choice from node {}", node),
+            None => write!(f, "start"),
+        };
+        match *self {
+            ExploreError::Fault { from: node, error } => {
+                from(f, node)?;
+                write!(f, ": {}", error)
+            }
+            ExploreError::StepLimit { from: node } => {
+                from(f, node)?;
+                write!(f, " ran for too many instructions without needing input")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExploreError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::program;
+
+    /// A droid in a corridor of four cells. Moving left (1) or right (2) outputs 0 if there is a
+    /// wall in the way, 2 when arriving at the rightmost cell and 1 otherwise.
+    const CORRIDOR: &str = "
+        loop:   add #0, #0, [cmd]
+                add #0, #0, [t]
+                in [cmd]
+                seq [cmd], #1, [t]
+                jnz [t], #left
+                seq [pos], #3, [t]
+                jnz [t], #wall
+                add [pos], #1, [pos]
+                jnz #1, #moved
+        left:   jez [pos], #wall
+                add [pos], #-1, [pos]
+        moved:  seq [pos], #3, [t]
+                add [t], #1, [t]
+                out [t]
+                jnz #1, #loop
+        wall:   out #0
+                jnz #1, #loop
+        pos:    data 0
+        cmd:    data 0
+        t:      data 0
+    ";
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    enum Move {
+        Left,
+        Right,
+    }
+
+    #[test]
+    fn corridor() {
+        let droid = program(CORRIDOR);
+        let mut explorer = Explorer::new(vec![(Move::Left, vec![1]), (Move::Right, vec![2])]);
+        let decode = |output: &[i64]| match output {
+            [0] => None,
+            &[status] => Some(status),
+            _ => unreachable!(),
+        };
+
+        let graph = explorer.explore(&droid, decode).unwrap();
+        assert_eq!(graph.nodes.len(), 4);
+        assert_eq!(graph.edges.len(), 6);
+        let end = graph.find(|&status| status == 2).unwrap();
+        assert_eq!(graph.nodes[end].depth, 3);
+        assert_eq!(graph.path(end), vec![&Move::Right; 3]);
+        assert_eq!(
+            graph.edges_from(end).collect::<Vec<_>>(),
+            vec![&Edge {
+                from: end,
+                to: 2,
+                choice: Move::Left
+            }]
+        );
+
+        explorer.set_order(Order::DepthFirst);
+        explorer.set_max_depth(Some(2));
+        let graph = explorer.explore(&droid, decode).unwrap();
+        assert_eq!(graph.nodes.len(), 3);
+        assert!(graph.find(|&status| status == 2).is_none());
+    }
+
+    #[test]
+    fn pending_input() {
+        let computer = program(
+            "
+            loop:   in [x]
+                    jez [x], #end
+                    add #0, #0, [x]
+                    out #1
+                    jnz #1, #loop
+            end:    hlt
+            x:      data 0
+            ",
+        );
+        let explorer = Explorer::new(vec![("stop", vec![0]), ("stop early", vec![0, 7])]);
+        let graph = explorer.explore(&computer, |_| Some(())).unwrap();
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.nodes[2].computer.pending_input(), 1);
+    }
+
+    #[test]
+    fn errors() {
+        let mut explorer = Explorer::new(vec![((), vec![1])]);
+        explorer.set_max_steps(Some(100));
+        let decode = |_: &[i64]| Some(());
+
+        let mut stuck = program("in [0]\nloop: jnz #1, #loop");
+        assert_eq!(
+            explorer.explore(&stuck, decode).err(),
+            Some(ExploreError::StepLimit { from: Some(0) })
+        );
+
+        stuck.provide_input(Some(1));
+        assert_eq!(
+            explorer.explore(&stuck, decode).err(),
+            Some(ExploreError::StepLimit { from: None })
+        );
+
+        let faulty = program("in [0]\ndata 0");
+        let error = explorer.explore(&faulty, decode).err().unwrap();
+        assert!(matches!(error, ExploreError::Fault { from: Some(0), .. }));
+    }
+}
@@ -0,0 +1,276 @@
+//! Running computers as futures, without spawning a thread for each of them.
+//!
+//! An [`AsyncComputer`] yields its outputs through [`AsyncComputer::next_output`], and suspends
+//! whenever it needs input until a value is sent through one of its [`InputSender`]s. Nothing here
+//! depends on a particular executor, but [`LocalExecutor`] is provided for running many computers
+//! on the current thread.
+
+use crate::{Action, Computer, IntcodeError};
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+pub struct AsyncComputer {
+    computer: Computer,
+    input: Arc<Mutex<InputQueue>>,
+}
+
+pub struct InputSender {
+    input: Arc<Mutex<InputQueue>>,
+}
+
+#[derive(Default)]
+struct InputQueue {
+    values: VecDeque<i64>,
+    waker: Option<Waker>,
+    senders: usize,
+}
+
+/// Future returned by [`AsyncComputer::next_output`].
+pub struct NextOutput<'a> {
+    computer: &'a mut AsyncComputer,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StreamError {
+    Fault(IntcodeError),
+    /// The computer needs input, but every [`InputSender`] has been dropped.
+    InputClosed,
+}
+
+impl Computer {
+    pub fn into_async(self) -> (AsyncComputer, InputSender) {
+        let computer = AsyncComputer {
+            computer: self,
+            input: Arc::default(),
+        };
+        let sender = computer.sender();
+        (computer, sender)
+    }
+}
+
+impl AsyncComputer {
+    pub fn sender(&self) -> InputSender {
+        self.input.lock().unwrap().senders += 1;
+        InputSender {
+            input: self.input.clone(),
+        }
+    }
+
+    /// Run until the next output. Resolves to `None` once the computer halts.
+    pub fn next_output(&mut self) -> NextOutput<'_> {
+        NextOutput { computer: self }
+    }
+
+    pub fn into_inner(self) -> Computer {
+        self.computer
+    }
+}
+
+impl Future for NextOutput<'_> {
+    type Output = Result<Option<i64>, StreamError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let AsyncComputer { computer, input } = &mut *self.get_mut().computer;
+
+        loop {
+            match computer.try_run() {
+                Ok(Action::Output(value)) => return Poll::Ready(Ok(Some(value))),
+                Ok(Action::Halt) => return Poll::Ready(Ok(None)),
+                Err(error) => return Poll::Ready(Err(StreamError::Fault(error))),
+                Ok(Action::NeedsInput) => {
+                    let mut input = input.lock().unwrap();
+                    if !input.values.is_empty() {
+                        computer.provide_input(input.values.drain(..));
+                    } else if input.senders == 0 {
+                        return Poll::Ready(Err(StreamError::InputClosed));
+                    } else {
+                        input.waker = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl InputSender {
+    pub fn send(&self, value: i64) {
+        self.extend(Some(value));
+    }
+
+    pub fn extend(&self, values: impl IntoIterator<Item = i64>) {
+        let mut input = self.input.lock().unwrap();
+        input.values.extend(values);
+        if let Some(waker) = input.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Clone for InputSender {
+    fn clone(&self) -> InputSender {
+        self.input.lock().unwrap().senders += 1;
+        InputSender {
+            input: self.input.clone(),
+        }
+    }
+}
+
+impl Drop for InputSender {
+    fn drop(&mut self) {
+        let mut input = self.input.lock().unwrap();
+        input.senders -= 1;
+        if input.senders == 0 {
+            if let Some(waker) = input.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// A minimal single-threaded executor.
+#[derive(Default)]
+pub struct LocalExecutor {
+    tasks: Vec<Option<Pin<Box<dyn Future<Output = ()>>>>>,
+    ready: Arc<Mutex<VecDeque<usize>>>,
+}
+
+struct TaskWaker {
+    task: usize,
+    ready: Arc<Mutex<VecDeque<usize>>>,
+}
+
+impl LocalExecutor {
+    pub fn new() -> LocalExecutor {
+        LocalExecutor::default()
+    }
+
+    pub fn spawn(&mut self, task: impl Future<Output = ()> + 'static) {
+        self.ready.lock().unwrap().push_back(self.tasks.len());
+        self.tasks.push(Some(Box::pin(task)));
+    }
+
+    /// Poll tasks, in the order they were woken, until no task can make progress. Returns the
+    /// number of tasks that did not complete, which are all waiting on something that will never
+    /// happen.
+    pub fn run(&mut self) -> usize {
+        loop {
+            let next = self.ready.lock().unwrap().pop_front();
+            let index = match next {
+                Some(index) => index,
+                None => break,
+            };
+
+            if let Some(task) = &mut self.tasks[index] {
+                let waker = Waker::from(Arc::new(TaskWaker {
+                    task: index,
+                    ready: self.ready.clone(),
+                }));
+                let mut cx = Context::from_waker(&waker);
+                if task.as_mut().poll(&mut cx).is_ready() {
+                    self.tasks[index] = None;
+                }
+            }
+        }
+
+        self.tasks.iter().filter(|task| task.is_some()).count()
+    }
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.ready.lock().unwrap().push_back(self.task);
+    }
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Fault(error) => write!(f, "{}", error),
+            StreamError::InputClosed => write!(f, "Input channel closed"),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn feedback_loop() {
+        // Example from day 7, part 2
+        let code = vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let phases = [9, 8, 7, 6, 5];
+
+        let (amps, senders): (Vec<_>, Vec<_>) = phases
+            .iter()
+            .map(|&phase| {
+                let (amp, sender) = Computer::new(code.clone()).into_async();
+                sender.send(phase);
+                (amp, sender)
+            })
+            .unzip();
+        senders[0].send(0);
+
+        let last = Rc::new(Cell::new(None));
+        let mut executor = LocalExecutor::new();
+
+        for (i, mut amp) in amps.into_iter().enumerate() {
+            let next = senders[(i + 1) % senders.len()].clone();
+            let last = last.clone();
+            executor.spawn(async move {
+                while let Some(value) = amp.next_output().await.unwrap() {
+                    next.send(value);
+                    if i == phases.len() - 1 {
+                        last.set(Some(value));
+                    }
+                }
+            });
+        }
+
+        drop(senders);
+        assert_eq!(executor.run(), 0);
+        assert_eq!(last.get(), Some(139629729));
+    }
+
+    #[test]
+    fn input_closed() {
+        let (mut computer, sender) = Computer::new(vec![3, 0, 4, 0, 99]).into_async();
+        drop(sender);
+
+        let result = Rc::new(Cell::new(None));
+        let mut executor = LocalExecutor::new();
+        executor.spawn({
+            let result = result.clone();
+            async move { result.set(Some(computer.next_output().await)) }
+        });
+
+        assert_eq!(executor.run(), 0);
+        assert_eq!(result.get(), Some(Err(StreamError::InputClosed)));
+    }
+
+    #[test]
+    fn deadlock() {
+        let (mut computer, sender) = Computer::new(vec![3, 0, 4, 0, 99]).into_async();
+
+        let mut executor = LocalExecutor::new();
+        executor.spawn(async move {
+            computer.next_output().await.unwrap();
+        });
+
+        assert_eq!(executor.run(), 1);
+        sender.send(5);
+        assert_eq!(executor.run(), 0);
+    }
+}
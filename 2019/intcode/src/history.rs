@@ -0,0 +1,302 @@
+//! Running programs backwards.
+//!
+//! With history enabled, every executed instruction records what it changed, so that it can be
+//! undone with [`Computer::step_back`]. Only changes made by instructions are recorded: memory
+//! written with [`Computer::poke`] and input provided with [`Computer::provide_input`] stay as
+//! they are when stepping back, except that consumed input is returned to the front of the queue.
+
+use crate::arith::BigInt;
+use crate::{Computer, Instruction, Observer};
+use std::collections::VecDeque;
+
+/// Number of instructions of history kept by default.
+const DEFAULT_LIMIT: usize = 1_000_000;
+
+#[derive(Debug, Clone)]
+pub(crate) struct History {
+    entries: VecDeque<Entry>,
+    limit: Option<usize>,
+    change: Change,
+    input: Option<i64>,
+    /// The wide value the current instruction is about to overwrite, if any.
+    wide: Option<(usize, BigInt)>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    instruction: usize,
+    change: Change,
+    /// The wide value that was overwritten, which `change` only holds the low bits of.
+    wide: Option<Box<BigInt>>,
+}
+
+/// The state an instruction changed, other than the instruction pointer.
+#[derive(Debug, Copy, Clone, Default)]
+enum Change {
+    #[default]
+    None,
+    Write {
+        address: usize,
+        old: i64,
+    },
+    Input {
+        address: usize,
+        old: i64,
+        value: i64,
+    },
+    RelativeBase(i64),
+}
+
+impl History {
+    fn new(limit: Option<usize>) -> History {
+        History {
+            entries: VecDeque::new(),
+            limit,
+            change: Change::None,
+            input: None,
+            wide: None,
+        }
+    }
+
+    pub fn begin(&mut self, computer: &Computer) {
+        self.change = Change::None;
+        self.input = None;
+        self.wide = None;
+        if !computer.wide.is_empty() {
+            self.wide = computer.write_target().and_then(|address| {
+                let value = computer.wide.get(&address)?;
+                Some((address, value.clone()))
+            });
+        }
+    }
+
+    /// Record the instruction at `instruction` as executed. `relative_base` is its value before
+    /// the instruction ran.
+    pub fn commit(&mut self, instruction: usize, relative_base: i64, computer: &Computer) {
+        if relative_base != computer.relative_base {
+            self.change = Change::RelativeBase(relative_base);
+        }
+        let written = match self.change {
+            Change::Write { address, .. } | Change::Input { address, .. } => Some(address),
+            _ => None,
+        };
+        let wide = match self.wide.take() {
+            Some((address, value)) if written == Some(address) => Some(Box::new(value)),
+            _ => None,
+        };
+        if Some(self.entries.len()) == self.limit {
+            self.entries.pop_front();
+        }
+        if self.limit != Some(0) {
+            self.entries.push_back(Entry {
+                instruction,
+                change: self.change,
+                wide,
+            });
+        }
+    }
+}
+
+impl Observer for History {
+    fn write(&mut self, address: usize, old: i64, _new: i64) {
+        self.change = match self.input.take() {
+            Some(value) => Change::Input {
+                address,
+                old,
+                value,
+            },
+            None => Change::Write { address, old },
+        };
+    }
+
+    fn input(&mut self, value: i64) {
+        self.input = Some(value);
+    }
+}
+
+impl Computer {
+    /// Start or stop recording history. Either way, any recorded history is discarded. By default
+    /// the last million instructions can be undone, see [`Computer::set_history_limit`].
+    pub fn set_history(&mut self, enabled: bool) {
+        self.history = if enabled {
+            Some(History::new(Some(DEFAULT_LIMIT)))
+        } else {
+            None
+        };
+    }
+
+    /// Keep at most `limit` instructions of history, forgetting the oldest ones first, or all of
+    /// them with `None`. Has no effect while history is disabled, and enabling it again restores
+    /// the default limit.
+    pub fn set_history_limit(&mut self, limit: Option<usize>) {
+        if let Some(history) = &mut self.history {
+            history.limit = limit;
+            let excess = history
+                .entries
+                .len()
+                .saturating_sub(limit.unwrap_or(usize::MAX));
+            history.entries.drain(..excess);
+        }
+    }
+
+    /// Number of instructions that can be undone. Once the limit is reached this stays the same
+    /// as instructions are executed, so it only identifies a point in the run to return to with
+    /// [`Computer::rewind_to`] while no history has been forgotten.
+    pub fn history_len(&self) -> usize {
+        self.history
+            .as_ref()
+            .map_or(0, |history| history.entries.len())
+    }
+
+    /// Undo the last executed instruction. Returns `false` if there is nothing to undo.
+    pub fn step_back(&mut self) -> bool {
+        let entry = match self
+            .history
+            .as_mut()
+            .and_then(|history| history.entries.pop_back())
+        {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        match entry.change {
+            Change::None => {}
+            Change::Write { address, old } => self.restore(address, old, entry.wide),
+            Change::Input {
+                address,
+                old,
+                value,
+            } => {
+                self.restore(address, old, entry.wide);
+                self.input.push_front(value);
+            }
+            Change::RelativeBase(relative_base) => self.relative_base = relative_base,
+        }
+
+        self.instruction = entry.instruction;
+        self.halted = false;
+        self.executed = self.executed.saturating_sub(1);
+        if let Some(transcript) = &mut self.transcript {
+            transcript.undo(self.executed);
+        }
+        true
+    }
+
+    /// Step back at least one instruction, until the instruction at `address` is about to be
+    /// executed again. Returns `false` if the history ran out first, in which case the computer is
+    /// left in the oldest recorded state.
+    pub fn run_back_to(&mut self, address: usize) -> bool {
+        while self.step_back() {
+            if self.instruction == address {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Step back until only `len` instructions of history remain.
+    pub fn rewind_to(&mut self, len: usize) {
+        while self.history_len() > len && self.step_back() {}
+    }
+
+    fn restore(&mut self, address: usize, value: i64, wide: Option<Box<BigInt>>) {
+        // Restoring a value that was already in memory can not need more memory
+        let _ = self.memory.set(address, value);
+        self.invalidate(address);
+        match wide {
+            Some(wide) => {
+                self.wide.insert(address, *wide);
+            }
+            None => {
+                self.wide.remove(&address);
+            }
+        }
+    }
+
+    /// The address the next instruction writes to, if it writes to memory.
+    fn write_target(&self) -> Option<usize> {
+        match self.instruction_at(self.instruction).ok()? {
+            Instruction::Add(_, _, target)
+            | Instruction::Mul(_, _, target)
+            | Instruction::Slt(_, _, target)
+            | Instruction::Seq(_, _, target)
+            | Instruction::Input(target) => self.index(target).ok(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{program, SUM};
+    use crate::{Action, Arithmetic};
+
+    #[test]
+    fn step_back_to_start() {
+        let mut computer = program(SUM);
+        computer.set_history(true);
+        computer.provide_input(vec![3, 4, 0]);
+        let initial = computer.snapshot();
+
+        let mut outputs = Vec::new();
+        while let Action::Output(value) = computer.run() {
+            outputs.push(value);
+        }
+        assert_eq!(outputs, vec![3, 7, 7]);
+        assert!(computer.is_halted());
+
+        assert!(computer.run_back_to(2));
+        assert_eq!(computer.pending_input(), 1);
+        assert_eq!(computer.run().output(), 7);
+
+        while computer.step_back() {}
+        assert_eq!(computer.history_len(), 0);
+        assert_eq!(computer.instruction_count(), 0);
+        assert_eq!(computer.snapshot(), initial);
+
+        assert!(!computer.run_back_to(0));
+    }
+
+    #[test]
+    fn limit() {
+        let mut computer = program(SUM);
+        computer.set_history(true);
+        computer.set_history_limit(Some(10));
+        computer.provide_input(vec![3, 4, 0]);
+        while let Action::Output(_) = computer.run() {}
+        assert_eq!(computer.history_len(), 10);
+
+        computer.set_history_limit(Some(3));
+        assert_eq!(computer.history_len(), 3);
+        let executed = computer.instruction_count();
+        computer.rewind_to(0);
+        assert_eq!(computer.instruction_count(), executed - 3);
+        assert!(!computer.step_back());
+    }
+
+    #[test]
+    fn restore_wide_value() {
+        let mut computer = program(
+            "
+                mul #4611686018427387904, #4, [x]
+                add #1, #2, [x]
+                hlt
+            x:  data 0
+            ",
+        );
+        computer.set_arithmetic(Arithmetic::Big);
+        computer.set_history(true);
+        computer.step().unwrap();
+        let wide = computer.wide.clone();
+        assert!(wide.contains_key(&9));
+        assert_eq!(computer.run(), Action::Halt);
+        assert!(computer.wide.is_empty());
+
+        computer.rewind_to(1);
+        assert_eq!(computer.wide, wide);
+        assert_eq!(computer.peek(9), 0);
+
+        assert!(computer.step_back());
+        assert!(computer.wide.is_empty());
+    }
+}
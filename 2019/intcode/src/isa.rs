@@ -0,0 +1,113 @@
+//! Restricting a computer to the instructions of an earlier puzzle.
+//!
+//! Programs from day 2 only use `add`, `mul` and `hlt` with position parameters. The first part of
+//! day 5 adds input, output and immediate parameters, and the second part adds jumps and
+//! comparisons. Running such programs at their own level makes anything they should not contain
+//! fault, instead of quietly doing what a later day would.
+
+use crate::{Computer, Fault, Instruction};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Isa {
+    /// `add`, `mul` and `hlt`, with position parameters only.
+    Day2,
+    /// Also `in` and `out`, and immediate parameters.
+    Day5Part1,
+    /// Also `jnz`, `jez`, `slt` and `seq`.
+    Day5,
+    /// Every instruction and parameter mode, adding `rb` and relative parameters.
+    #[default]
+    Full,
+}
+
+impl Isa {
+    pub(crate) fn check(self, instruction: Instruction) -> Result<(), Fault> {
+        let (max_opcode, max_mode) = match self {
+            Isa::Day2 => (2, 0),
+            Isa::Day5Part1 => (4, 1),
+            Isa::Day5 => (8, 1),
+            Isa::Full => return Ok(()),
+        };
+
+        if instruction != Instruction::Halt && instruction.opcode() > max_opcode {
+            return Err(Fault::InvalidOpcode);
+        }
+
+        for (parameter, param) in instruction.parameters().into_iter().enumerate() {
+            let mode = param.mode();
+            if mode > max_mode {
+                return Err(Fault::InvalidMode {
+                    parameter: parameter as u32,
+                    mode,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Computer {
+    /// Only accept instructions and parameter modes available at the given level. Instructions
+    /// that are not fault with [`Fault::InvalidOpcode`] or [`Fault::InvalidMode`].
+    pub fn set_isa(&mut self, isa: Isa) {
+        self.isa = isa;
+    }
+
+    pub fn isa(&self) -> Isa {
+        self.isa
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Action;
+
+    #[test]
+    fn levels() {
+        let mut computer = Computer::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        computer.set_isa(Isa::Day2);
+        computer.run();
+        assert_eq!(computer.peek(0), 3500);
+
+        // mul [4], #3, [4]
+        let mut computer = Computer::new(vec![1002, 4, 3, 4, 33]);
+        computer.set_isa(Isa::Day2);
+        let error = computer.try_run().unwrap_err();
+        assert_eq!(
+            error.fault,
+            Fault::InvalidMode {
+                parameter: 1,
+                mode: 1
+            }
+        );
+        computer.set_isa(Isa::Day5Part1);
+        computer.run();
+        assert_eq!(computer.peek(4), 99);
+
+        // jnz #1, #3, hlt
+        let mut computer = Computer::new(vec![1105, 1, 3, 99]);
+        computer.set_isa(Isa::Day5Part1);
+        assert_eq!(computer.try_run().unwrap_err().fault, Fault::InvalidOpcode);
+        computer.set_isa(Isa::Day5);
+        assert_eq!(computer.run(), Action::Halt);
+
+        // rb #1
+        let mut computer = Computer::new(vec![109, 1, 99]);
+        computer.set_isa(Isa::Day5);
+        assert_eq!(computer.try_run().unwrap_err().fault, Fault::InvalidOpcode);
+
+        // out rb[0]
+        let mut computer = Computer::new(vec![204, 0, 99]);
+        computer.set_isa(Isa::Day5);
+        let error = computer.try_run().unwrap_err();
+        assert_eq!(
+            error.fault,
+            Fault::InvalidMode {
+                parameter: 0,
+                mode: 2
+            }
+        );
+    }
+}
@@ -1,20 +1,71 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::convert::TryInto;
+use std::fmt;
 use std::fs;
 use std::path::Path;
 use std::sync::mpsc::{channel, Iter as OutputIter, Receiver, RecvError, SendError, Sender};
 use std::thread;
 
+use arith::BigInt;
+use cache::DecodeCache;
+use history::History;
+use observer::Pair;
+use watch::Watchpoints;
+
+pub mod analysis;
+mod arith;
+pub mod ascii;
+pub mod asm;
+pub mod batch;
+mod cache;
+pub mod compile;
+pub mod disasm;
+pub mod explore;
+pub mod future;
+mod history;
+mod isa;
+pub mod memory;
+pub mod network;
+pub mod observer;
+pub mod pipeline;
+pub mod profile;
+pub mod snapshot;
+pub mod stream;
+pub mod transcript;
+pub mod watch;
+
+#[cfg(test)]
+mod test_support;
+
+pub use arith::Arithmetic;
+pub use isa::Isa;
+pub use memory::Memory;
+pub use observer::Observer;
+pub use profile::Profile;
+pub use stream::{InputSource, OutputSink};
+pub use transcript::Transcript;
+pub use watch::Watch;
+
 pub type Error = Box<dyn std::error::Error>;
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 #[derive(Clone)]
 pub struct Computer {
     instruction: usize,
-    code: Vec<i64>,
+    memory: Memory,
     input: VecDeque<i64>,
     relative_base: i64,
     halted: bool,
+    executed: u64,
+    cache: Option<DecodeCache>,
+    profile: Option<Box<Profile>>,
+    history: Option<History>,
+    watchpoints: Watchpoints,
+    transcript: Option<Transcript>,
+    arithmetic: Arithmetic,
+    /// Values that do not fit in 64 bits, by address, with [`Arithmetic::Big`].
+    wide: HashMap<usize, BigInt>,
+    isa: Isa,
 }
 
 pub struct Input(Sender<i64>);
@@ -25,8 +76,8 @@ pub struct Io {
     output: Output,
 }
 
-#[derive(Debug, Copy, Clone)]
-enum Instruction {
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Instruction {
     Halt,
     Add(Parameter, Parameter, Address),
     Mul(Parameter, Parameter, Address),
@@ -39,33 +90,62 @@ enum Instruction {
     Reb(Parameter),
 }
 
-#[derive(Debug, Copy, Clone)]
-enum Parameter {
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Parameter {
     Address(Address),
     Value(i64),
 }
 
-#[derive(Debug, Copy, Clone)]
-enum Address {
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Address {
     Position(i64),
     Relative(i64),
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Action {
     Output(i64),
     NeedsInput,
     Halt,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IntcodeError {
+    pub fault: Fault,
+    /// Address of the instruction that faulted.
+    pub instruction: usize,
+    /// The raw opcode of the faulting instruction, including parameter modes.
+    pub opcode: i64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Fault {
+    InvalidOpcode,
+    InvalidMode { parameter: u32, mode: i64 },
+    NegativeAddress(i64),
+    WriteToImmediate { parameter: u32 },
+    NegativeJump(i64),
+    OutOfMemory(usize),
+    Overflow,
+}
+
 impl Computer {
     pub fn new(code: Vec<i64>) -> Computer {
         Computer {
             instruction: 0,
-            code,
+            memory: Memory::new(code),
             input: VecDeque::new(),
             relative_base: 0,
             halted: false,
+            executed: 0,
+            cache: None,
+            profile: None,
+            history: None,
+            transcript: None,
+            arithmetic: Arithmetic::default(),
+            wide: HashMap::new(),
+            isa: Isa::default(),
+            watchpoints: Watchpoints::default(),
         }
     }
 
@@ -81,93 +161,106 @@ impl Computer {
         Ok(Computer::new(code))
     }
 
-    fn validate_index(&mut self, index: usize) {
-        if index >= self.code.len() {
-            self.code.resize(index + 1, 0);
-        }
+    fn index(&self, address: Address) -> Result<usize, Fault> {
+        let index = match address {
+            Address::Position(index) => index,
+            Address::Relative(relative) => self.arithmetic.add(self.relative_base, relative)?,
+        };
+        index.try_into().map_err(|_| Fault::NegativeAddress(index))
     }
 
-    fn index(&self, address: Address) -> usize {
-        match address {
-            Address::Position(index) => index.try_into().unwrap(),
-            Address::Relative(relative) => {
-                let index = self.relative_base as i64 + relative;
-                index.try_into().unwrap()
-            }
+    /// Read a parameter. Unless `CHECKED` is set, there must be no wide values.
+    fn read<const CHECKED: bool>(
+        &mut self,
+        address: Address,
+        observer: &mut impl Observer,
+    ) -> Result<i64, Fault> {
+        let index = self.index(address)?;
+        if CHECKED && !self.wide.is_empty() && self.wide.contains_key(&index) {
+            return Err(Fault::Overflow);
         }
+        let value = self.memory.get(index);
+        observer.read(index, value);
+        Ok(value)
     }
 
-    fn read(&mut self, address: Address) -> i64 {
-        let index = self.index(address);
-        self.validate_index(index);
-        self.code[index]
-    }
-
-    fn write(&mut self, value: i64, address: Address) {
-        let index = self.index(address);
-        self.validate_index(index);
-        self.code[index] = value;
+    fn write<const CHECKED: bool>(
+        &mut self,
+        value: i64,
+        address: Address,
+        observer: &mut impl Observer,
+    ) -> Result<(), Fault> {
+        let index = self.index(address)?;
+        let old = self.memory.set(index, value)?;
+        self.invalidate(index);
+        if CHECKED && !self.wide.is_empty() {
+            self.wide.remove(&index);
+        }
+        observer.write(index, old, value);
+        Ok(())
     }
 
-    fn evaluate(&mut self, param: Parameter) -> i64 {
+    fn evaluate<const CHECKED: bool>(
+        &mut self,
+        param: Parameter,
+        observer: &mut impl Observer,
+    ) -> Result<i64, Fault> {
         match param {
-            Parameter::Address(address) => self.read(address),
-            Parameter::Value(value) => value,
+            Parameter::Address(address) => self.read::<CHECKED>(address, observer),
+            Parameter::Value(value) => Ok(value),
         }
     }
 
-    fn fetch_int(&mut self) -> i64 {
-        let value = self.read(Address::Position(self.instruction as i64));
-        self.instruction += 1;
-        value
+    fn jump<const CHECKED: bool>(
+        &mut self,
+        target: Parameter,
+        observer: &mut impl Observer,
+    ) -> Result<(), Fault> {
+        let target = self.evaluate::<CHECKED>(target, observer)?;
+        self.instruction = target.try_into().map_err(|_| Fault::NegativeJump(target))?;
+        Ok(())
     }
 
-    fn parameter_kind(digit: i64) -> fn(i64) -> Parameter {
-        match digit {
-            0 => |value| Parameter::Address(Address::Position(value)),
-            1 => Parameter::Value,
-            2 => |value| Parameter::Address(Address::Relative(value)),
-            _ => panic!("Invalid parameter kind: {}", digit),
-        }
+    fn fetch_int(&mut self) -> Result<i64, Fault> {
+        let value = self.memory.get(self.instruction);
+        self.instruction += 1;
+        Ok(value)
     }
 
-    fn fetch_instruction(&mut self) -> Instruction {
-        let instruction = self.fetch_int();
+    fn fetch_instruction(&mut self) -> Result<Instruction, Fault> {
+        let address = self.instruction;
 
-        let a = Self::parameter_kind(digit(instruction, 2));
-        let b = Self::parameter_kind(digit(instruction, 3));
-        let c = Self::parameter_kind(digit(instruction, 4));
+        if let Some(cache) = &self.cache {
+            if let Some(instruction) = cache.get(address) {
+                self.instruction += instruction.size();
+                return Ok(instruction);
+            }
+        }
 
-        let op = instruction % 100;
+        let instruction = Instruction::decode(|| self.fetch_int())?;
+        if let Some(cache) = &mut self.cache {
+            cache.insert(address, instruction);
+        }
+        Ok(instruction)
+    }
 
-        match op {
-            99 => Instruction::Halt,
-            1 => Instruction::Add(
-                a(self.fetch_int()),
-                b(self.fetch_int()),
-                c(self.fetch_int()).address().unwrap(),
-            ),
-            2 => Instruction::Mul(
-                a(self.fetch_int()),
-                b(self.fetch_int()),
-                c(self.fetch_int()).address().unwrap(),
-            ),
-            3 => Instruction::Input(a(self.fetch_int()).address().unwrap()),
-            4 => Instruction::Output(a(self.fetch_int())),
-            5 => Instruction::Jnz(a(self.fetch_int()), b(self.fetch_int())),
-            6 => Instruction::Jez(a(self.fetch_int()), b(self.fetch_int())),
-            7 => Instruction::Slt(
-                a(self.fetch_int()),
-                b(self.fetch_int()),
-                c(self.fetch_int()).address().unwrap(),
-            ),
-            8 => Instruction::Seq(
-                a(self.fetch_int()),
-                b(self.fetch_int()),
-                c(self.fetch_int()).address().unwrap(),
-            ),
-            9 => Instruction::Reb(a(self.fetch_int())),
-            _ => panic!("Invalid opcode: {}", op),
+    fn invalidate(&mut self, address: usize) {
+        if let Some(cache) = &mut self.cache {
+            cache.invalidate(address);
+        }
+    }
+
+    /// Cache decoded instructions, so that they do not have to be decoded again the next time they
+    /// are executed. Cached instructions are discarded when their memory is written to, so
+    /// self-modifying programs behave the same either way. A clone keeps the setting, but starts
+    /// with an empty cache.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        if enabled != self.cache.is_some() {
+            self.cache = if enabled {
+                Some(DecodeCache::default())
+            } else {
+                None
+            };
         }
     }
 
@@ -180,68 +273,340 @@ impl Computer {
         self.input.extend(input);
     }
 
+    pub fn pending_input(&self) -> usize {
+        self.input.len()
+    }
+
+    /// Discard any input that has not been consumed yet.
+    pub fn clear_input(&mut self) {
+        self.input.clear();
+    }
+
+    pub fn instruction_pointer(&self) -> usize {
+        self.instruction
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Total number of instructions executed by this computer. Instructions that are waiting for
+    /// input or that fault are not counted.
+    pub fn instruction_count(&self) -> u64 {
+        self.executed
+    }
+
+    pub fn reset_instruction_count(&mut self) {
+        self.executed = 0;
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// Limit the number of memory cells the program may allocate. See [`Memory::set_limit`].
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory.set_limit(limit);
+    }
+
+    pub fn peek(&self, address: usize) -> i64 {
+        self.memory.get(address)
+    }
+
+    pub fn poke(&mut self, address: usize, value: i64) -> Result<(), Fault> {
+        self.memory.set(address, value)?;
+        self.invalidate(address);
+        if !self.wide.is_empty() {
+            self.wide.remove(&address);
+        }
+        Ok(())
+    }
+
+    /// Decode the instruction at the given address without executing it.
+    pub fn instruction_at(&self, address: usize) -> Result<Instruction, Fault> {
+        let mut next = Some(address);
+        Instruction::decode(|| {
+            let address = next.ok_or(Fault::Overflow)?;
+            next = address.checked_add(1);
+            Ok(self.peek(address))
+        })
+    }
+
     pub fn run(&mut self) -> Action {
-        if self.halted {
-            return Action::Halt
+        self.run_with(&mut ())
+    }
+
+    pub fn try_run(&mut self) -> Result<Action, IntcodeError> {
+        self.try_run_with(&mut ())
+    }
+
+    /// Execute a single instruction. Returns `None` if the instruction completed without
+    /// producing an action. On error the instruction pointer is left at the faulting instruction.
+    pub fn step(&mut self) -> Result<Option<Action>, IntcodeError> {
+        self.step_with(&mut ())
+    }
+
+    /// Run until the next action, or until `budget` instructions have been executed, in which
+    /// case `None` is returned and the computer can be resumed later.
+    pub fn run_with_budget(&mut self, budget: u64) -> Option<Action> {
+        match self.try_run_with_budget(budget) {
+            Ok(action) => action,
+            Err(error) => panic!("{}", error),
         }
+    }
 
-        loop {
-            let instruction = self.fetch_instruction();
+    pub fn try_run_with_budget(&mut self, budget: u64) -> Result<Option<Action>, IntcodeError> {
+        for _ in 0..budget {
+            if let Some(action) = self.step()? {
+                return Ok(Some(action));
+            }
+        }
 
-            match instruction {
-                Instruction::Halt => {
-                    self.halted = true;
-                    return Action::Halt
-                },
-                Instruction::Add(a, b, target) => {
-                    let lhs = self.evaluate(a);
-                    let rhs = self.evaluate(b);
-                    self.write(lhs + rhs, target);
-                }
-                Instruction::Mul(a, b, target) => {
-                    let lhs = self.evaluate(a);
-                    let rhs = self.evaluate(b);
-                    self.write(lhs * rhs, target);
-                }
-                Instruction::Input(target) => {
-                    if let Some(value) = self.input.pop_front() {
-                        self.write(value, target);
-                    } else {
-                        self.rollback(instruction);
-                        return Action::NeedsInput;
-                    }
-                }
-                Instruction::Output(parameter) => {
-                    let value = self.evaluate(parameter);
-                    return Action::Output(value);
+        Ok(None)
+    }
+
+    pub fn run_with(&mut self, observer: &mut impl Observer) -> Action {
+        match self.try_run_with(observer) {
+            Ok(action) => action,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    pub fn try_run_with(&mut self, observer: &mut impl Observer) -> Result<Action, IntcodeError> {
+        // Hooks can only be enabled between runs, so checking them once is enough
+        if self.hooked() {
+            loop {
+                if let Some(action) = self.step_hooked(observer)? {
+                    return Ok(action);
                 }
-                Instruction::Jnz(a, target) => {
-                    let value = self.evaluate(a);
-                    if value != 0 {
-                        self.instruction = self.evaluate(target).try_into().unwrap();
-                    }
+            }
+        }
+        if self.checked() {
+            self.run_plain::<true>(observer)
+        } else {
+            self.run_plain::<false>(observer)
+        }
+    }
+
+    pub fn step_with(
+        &mut self,
+        observer: &mut impl Observer,
+    ) -> Result<Option<Action>, IntcodeError> {
+        if self.hooked() {
+            self.step_hooked(observer)
+        } else if self.checked() {
+            self.step_plain::<true>(observer)
+        } else {
+            self.step_plain::<false>(observer)
+        }
+    }
+
+    /// Move the computer to where natively compiled code left off, after it executed `executed`
+    /// instructions. See [`compile`].
+    pub(crate) fn sync(&mut self, instruction: usize, relative_base: i64, executed: u64) {
+        self.instruction = instruction;
+        self.relative_base = relative_base;
+        self.executed += executed;
+    }
+
+    /// Whether the computer does anything while running that compiled code does not, such as
+    /// recording every instruction or checking arithmetic.
+    pub(crate) fn interpreted_only(&self) -> bool {
+        self.hooked() || self.checked()
+    }
+
+    pub(crate) fn next_input(&self) -> Option<i64> {
+        self.input.front().copied()
+    }
+
+    pub(crate) fn take_input(&mut self) -> Option<i64> {
+        self.input.pop_front()
+    }
+
+    /// Whether anything records the execution of every instruction.
+    fn hooked(&self) -> bool {
+        self.profile.is_some() || self.history.is_some() || self.transcript.is_some()
+    }
+
+    fn run_plain<const CHECKED: bool>(
+        &mut self,
+        observer: &mut impl Observer,
+    ) -> Result<Action, IntcodeError> {
+        loop {
+            if let Some(action) = self.step_plain::<CHECKED>(observer)? {
+                return Ok(action);
+            }
+        }
+    }
+
+    // Without this the run loop is not compiled into a single function, and is about half as fast
+    #[inline(always)]
+    fn step_plain<const CHECKED: bool>(
+        &mut self,
+        observer: &mut impl Observer,
+    ) -> Result<Option<Action>, IntcodeError> {
+        if self.halted {
+            return Ok(Some(Action::Halt));
+        }
+
+        let start = self.instruction;
+        let result = self.execute::<CHECKED>(observer);
+        self.complete(start, result)
+    }
+
+    fn step_hooked(
+        &mut self,
+        observer: &mut impl Observer,
+    ) -> Result<Option<Action>, IntcodeError> {
+        if self.halted {
+            return Ok(Some(Action::Halt));
+        }
+
+        let start = self.instruction;
+        let relative_base = self.relative_base;
+
+        let mut profile = self.profile.take();
+        let mut history = self.history.take();
+        let mut transcript = self.transcript.take();
+        if let Some(history) = &mut history {
+            history.begin(self);
+        }
+        if let Some(transcript) = &mut transcript {
+            transcript.begin(self.executed);
+        }
+        let hooks = Pair(Pair(&mut profile, &mut history), &mut transcript);
+        let result = self.execute::<true>(&mut Pair(hooks, observer));
+        if let Ok(action) = &result {
+            if !matches!(action, Some(Action::NeedsInput)) {
+                if let Some(profile) = &mut profile {
+                    profile.commit();
                 }
-                Instruction::Jez(a, target) => {
-                    let value = self.evaluate(a);
-                    if value == 0 {
-                        self.instruction = self.evaluate(target).try_into().unwrap();
-                    }
+                if let Some(history) = &mut history {
+                    history.commit(start, relative_base, self);
                 }
-                Instruction::Slt(a, b, target) => {
-                    let lhs = self.evaluate(a);
-                    let rhs = self.evaluate(b);
-                    self.write(if lhs < rhs { 1 } else { 0 }, target);
+            }
+        }
+        self.profile = profile;
+        self.history = history;
+        self.transcript = transcript;
+
+        self.complete(start, result)
+    }
+
+    /// Count a completed instruction, or turn a fault into an error.
+    fn complete(
+        &mut self,
+        start: usize,
+        result: Result<Option<Action>, Fault>,
+    ) -> Result<Option<Action>, IntcodeError> {
+        match result {
+            Ok(Some(Action::NeedsInput)) => Ok(Some(Action::NeedsInput)),
+            Ok(action) => {
+                self.executed += 1;
+                Ok(action)
+            }
+            Err(fault) => {
+                self.instruction = start;
+                Err(IntcodeError {
+                    fault,
+                    instruction: start,
+                    opcode: self.memory.get(start),
+                })
+            }
+        }
+    }
+
+    /// Whether instructions or arithmetic need to be checked.
+    fn checked(&self) -> bool {
+        self.isa != Isa::Full || self.arithmetic != Arithmetic::Wrapping
+    }
+
+    /// Execute the next instruction. Unless `CHECKED` is set, the computer must use the full
+    /// instruction set and wrapping arithmetic.
+    fn execute<const CHECKED: bool>(
+        &mut self,
+        observer: &mut impl Observer,
+    ) -> Result<Option<Action>, Fault> {
+        let address = self.instruction;
+        let instruction = self.fetch_instruction()?;
+        if CHECKED && self.isa != Isa::Full {
+            self.isa.check(instruction)?;
+        }
+        observer.instruction(address, instruction);
+
+        if CHECKED
+            && self.arithmetic == Arithmetic::Big
+            && self.execute_big(address, instruction, observer)?
+        {
+            return Ok(None);
+        }
+
+        match instruction {
+            Instruction::Halt => {
+                self.halted = true;
+                return Ok(Some(Action::Halt));
+            }
+            Instruction::Add(a, b, target) => {
+                let lhs = self.evaluate::<CHECKED>(a, observer)?;
+                let rhs = self.evaluate::<CHECKED>(b, observer)?;
+                self.write::<CHECKED>(self.arithmetic.add(lhs, rhs)?, target, observer)?;
+            }
+            Instruction::Mul(a, b, target) => {
+                let lhs = self.evaluate::<CHECKED>(a, observer)?;
+                let rhs = self.evaluate::<CHECKED>(b, observer)?;
+                self.write::<CHECKED>(self.arithmetic.mul(lhs, rhs)?, target, observer)?;
+            }
+            Instruction::Input(target) => {
+                let index = self.index(target)?;
+                if let Some(&value) = self.input.front() {
+                    // Make sure the value can be written before consuming it
+                    self.memory.reserve(index, value)?;
+                    self.input.pop_front();
+                    observer.input(value);
+                    self.write::<CHECKED>(value, target, observer)?;
+                } else {
+                    self.rollback(instruction);
+                    return Ok(Some(Action::NeedsInput));
                 }
-                Instruction::Seq(a, b, target) => {
-                    let lhs = self.evaluate(a);
-                    let rhs = self.evaluate(b);
-                    self.write(if lhs == rhs { 1 } else { 0 }, target);
+            }
+            Instruction::Output(parameter) => {
+                let value = self.evaluate::<CHECKED>(parameter, observer)?;
+                observer.output(value);
+                return Ok(Some(Action::Output(value)));
+            }
+            Instruction::Jnz(a, target) => {
+                let value = self.evaluate::<CHECKED>(a, observer)?;
+                if value != 0 {
+                    self.jump::<CHECKED>(target, observer)?;
                 }
-                Instruction::Reb(offset) => {
-                    self.relative_base += self.evaluate(offset);
+            }
+            Instruction::Jez(a, target) => {
+                let value = self.evaluate::<CHECKED>(a, observer)?;
+                if value == 0 {
+                    self.jump::<CHECKED>(target, observer)?;
                 }
             }
+            Instruction::Slt(a, b, target) => {
+                let lhs = self.evaluate::<CHECKED>(a, observer)?;
+                let rhs = self.evaluate::<CHECKED>(b, observer)?;
+                self.write::<CHECKED>(if lhs < rhs { 1 } else { 0 }, target, observer)?;
+            }
+            Instruction::Seq(a, b, target) => {
+                let lhs = self.evaluate::<CHECKED>(a, observer)?;
+                let rhs = self.evaluate::<CHECKED>(b, observer)?;
+                self.write::<CHECKED>(if lhs == rhs { 1 } else { 0 }, target, observer)?;
+            }
+            Instruction::Reb(offset) => {
+                let offset = self.evaluate::<CHECKED>(offset, observer)?;
+                self.relative_base = self.arithmetic.add(self.relative_base, offset)?;
+            }
         }
+
+        Ok(None)
     }
 
     pub fn run_async(mut self) -> Io {
@@ -286,7 +651,7 @@ impl Output {
         self.0.recv()
     }
 
-    pub fn iter(&self) -> OutputIter<Action> {
+    pub fn iter(&self) -> OutputIter<'_, Action> {
         self.0.iter()
     }
 }
@@ -300,12 +665,86 @@ impl Io {
         self.output.recv()
     }
 
-    pub fn iter(&self) -> OutputIter<Action> {
+    pub fn iter(&self) -> OutputIter<'_, Action> {
         self.output.iter()
     }
 }
 
 impl Instruction {
+    /// Decode an instruction, calling `fetch` once for the opcode and once for each parameter.
+    fn decode(mut fetch: impl FnMut() -> Result<i64, Fault>) -> Result<Instruction, Fault> {
+        let opcode = fetch()?;
+
+        let mut parameter = |parameter: u32| -> Result<Parameter, Fault> {
+            let value = fetch()?;
+            match digit(opcode, parameter + 2) {
+                0 => Ok(Parameter::Address(Address::Position(value))),
+                1 => Ok(Parameter::Value(value)),
+                2 => Ok(Parameter::Address(Address::Relative(value))),
+                mode => Err(Fault::InvalidMode { parameter, mode }),
+            }
+        };
+
+        let instruction = match opcode % 100 {
+            99 => Instruction::Halt,
+            1 => Instruction::Add(parameter(0)?, parameter(1)?, target(parameter(2)?, 2)?),
+            2 => Instruction::Mul(parameter(0)?, parameter(1)?, target(parameter(2)?, 2)?),
+            3 => Instruction::Input(target(parameter(0)?, 0)?),
+            4 => Instruction::Output(parameter(0)?),
+            5 => Instruction::Jnz(parameter(0)?, parameter(1)?),
+            6 => Instruction::Jez(parameter(0)?, parameter(1)?),
+            7 => Instruction::Slt(parameter(0)?, parameter(1)?, target(parameter(2)?, 2)?),
+            8 => Instruction::Seq(parameter(0)?, parameter(1)?, target(parameter(2)?, 2)?),
+            9 => Instruction::Reb(parameter(0)?),
+            _ => return Err(Fault::InvalidOpcode),
+        };
+
+        Ok(instruction)
+    }
+
+    /// Encode the instruction into its opcode followed by its parameters.
+    pub fn encode(self) -> Vec<i64> {
+        let parameters = self.parameters();
+
+        let mut opcode = self.opcode();
+        for (i, parameter) in parameters.iter().enumerate() {
+            opcode += parameter.mode() * 10i64.pow(i as u32 + 2);
+        }
+
+        Some(opcode)
+            .into_iter()
+            .chain(parameters.iter().map(|parameter| parameter.value()))
+            .collect()
+    }
+
+    pub fn opcode(self) -> i64 {
+        match self {
+            Instruction::Halt => 99,
+            Instruction::Add(_, _, _) => 1,
+            Instruction::Mul(_, _, _) => 2,
+            Instruction::Input(_) => 3,
+            Instruction::Output(_) => 4,
+            Instruction::Jnz(_, _) => 5,
+            Instruction::Jez(_, _) => 6,
+            Instruction::Slt(_, _, _) => 7,
+            Instruction::Seq(_, _, _) => 8,
+            Instruction::Reb(_) => 9,
+        }
+    }
+
+    pub fn parameters(self) -> Vec<Parameter> {
+        match self {
+            Instruction::Halt => vec![],
+            Instruction::Add(a, b, c)
+            | Instruction::Mul(a, b, c)
+            | Instruction::Slt(a, b, c)
+            | Instruction::Seq(a, b, c) => vec![a, b, Parameter::Address(c)],
+            Instruction::Input(a) => vec![Parameter::Address(a)],
+            Instruction::Output(a) | Instruction::Reb(a) => vec![a],
+            Instruction::Jnz(a, b) | Instruction::Jez(a, b) => vec![a, b],
+        }
+    }
+
     pub fn size(self) -> usize {
         match self {
             Instruction::Halt => 1,
@@ -331,6 +770,46 @@ impl Action {
     }
 }
 
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (instruction {}, opcode {})",
+            self.fault, self.instruction, self.opcode
+        )
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::InvalidOpcode => write!(f, "Invalid opcode"),
+            Fault::InvalidMode { parameter, mode } => {
+                write!(
+                    f,
+                    "Invalid parameter kind {} for parameter {}",
+                    mode, parameter
+                )
+            }
+            Fault::NegativeAddress(address) => write!(f, "Negative address: {}", address),
+            Fault::WriteToImmediate { parameter } => {
+                write!(f, "Write to immediate parameter {}", parameter)
+            }
+            Fault::NegativeJump(target) => write!(f, "Jump to negative address: {}", target),
+            Fault::OutOfMemory(address) => write!(f, "Out of memory writing to {}", address),
+            Fault::Overflow => write!(f, "Arithmetic overflow"),
+        }
+    }
+}
+
+impl std::error::Error for IntcodeError {}
+
+fn target(parameter: Parameter, index: u32) -> Result<Address, Fault> {
+    parameter
+        .address()
+        .ok_or(Fault::WriteToImmediate { parameter: index })
+}
+
 fn digit(value: i64, digit: u32) -> i64 {
     (value / 10i64.pow(digit)) % 10
 }
@@ -356,4 +835,154 @@ impl Parameter {
             _ => None,
         }
     }
+
+    pub fn mode(self) -> i64 {
+        match self {
+            Parameter::Address(Address::Position(_)) => 0,
+            Parameter::Value(_) => 1,
+            Parameter::Address(Address::Relative(_)) => 2,
+        }
+    }
+
+    pub fn value(self) -> i64 {
+        match self {
+            Parameter::Address(Address::Position(value)) => value,
+            Parameter::Value(value) => value,
+            Parameter::Address(Address::Relative(value)) => value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quine() {
+        let code = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        assert_eq!(compute(code.clone(), None), code);
+    }
+
+    #[test]
+    fn invalid_opcode() {
+        let mut computer = Computer::new(vec![1101, 1, 2, 5, 42, 99]);
+        let error = computer.try_run().unwrap_err();
+        assert_eq!(error.fault, Fault::InvalidOpcode);
+        assert_eq!(error.instruction, 4);
+        assert_eq!(error.opcode, 42);
+    }
+
+    #[test]
+    fn invalid_mode() {
+        let mut computer = Computer::new(vec![3001, 1, 2, 3, 99]);
+        let error = computer.try_run().unwrap_err();
+        assert_eq!(
+            error.fault,
+            Fault::InvalidMode {
+                parameter: 1,
+                mode: 3
+            }
+        );
+    }
+
+    #[test]
+    fn negative_address() {
+        let mut computer = Computer::new(vec![4, -7, 99]);
+        let error = computer.try_run().unwrap_err();
+        assert_eq!(error.fault, Fault::NegativeAddress(-7));
+    }
+
+    #[test]
+    fn write_to_immediate() {
+        let mut computer = Computer::new(vec![11101, 1, 2, 3, 99]);
+        let error = computer.try_run().unwrap_err();
+        assert_eq!(error.fault, Fault::WriteToImmediate { parameter: 2 });
+    }
+
+    #[test]
+    fn negative_jump() {
+        let mut computer = Computer::new(vec![1105, 1, -2, 99]);
+        let error = computer.try_run().unwrap_err();
+        assert_eq!(error.fault, Fault::NegativeJump(-2));
+        assert_eq!(error.instruction, 0);
+    }
+
+    #[test]
+    fn relative_address_overflow() {
+        // rb #MAX, out rb[1]
+        let program = Computer::new(vec![109, i64::MAX, 204, 1, 99]);
+
+        let error = program.clone().try_run().unwrap_err();
+        assert_eq!(error.fault, Fault::NegativeAddress(i64::MIN));
+        assert_eq!(error.instruction, 2);
+
+        let mut computer = program.clone();
+        computer.set_arithmetic(Arithmetic::Checked);
+        let error = computer.try_run().unwrap_err();
+        assert_eq!(error.fault, Fault::Overflow);
+        assert_eq!(error.instruction, 2);
+    }
+
+    #[test]
+    fn instruction_at_end_of_memory() {
+        let mut computer = Computer::new(vec![]);
+        computer.poke(usize::MAX, 99).unwrap();
+        assert_eq!(computer.instruction_at(usize::MAX), Ok(Instruction::Halt));
+        computer.poke(usize::MAX, 4).unwrap();
+        assert_eq!(computer.instruction_at(usize::MAX), Err(Fault::Overflow));
+    }
+
+    #[test]
+    fn self_modifying() {
+        // Counts down from 3, patching its own output instruction to print the counter
+        let code = vec![
+            1001, 17, -1, 17, // add [17], #-1, [17]
+            1001, 17, 0, 12, // add [17], #0, [12]
+            1105, 1, 11, // jnz #1, #11
+            104, 0, // out #0
+            1005, 17, 0, // jnz [17], #0
+            99, 3,
+        ];
+
+        for &cached in &[false, true] {
+            let mut computer = Computer::new(code.clone());
+            computer.set_decode_cache(cached);
+            let mut output = Vec::new();
+            while let Action::Output(value) = computer.run() {
+                output.push(value);
+            }
+            assert_eq!(output, vec![2, 1, 0]);
+        }
+    }
+
+    #[test]
+    fn budget() {
+        let mut computer = Computer::new(vec![1105, 1, 0]);
+        assert!(computer.run_with_budget(100).is_none());
+        assert!(computer.run_with_budget(50).is_none());
+        assert_eq!(computer.instruction_count(), 150);
+
+        let mut computer = Computer::new(vec![3, 0, 4, 0, 99]);
+        assert!(matches!(
+            computer.run_with_budget(10),
+            Some(Action::NeedsInput)
+        ));
+        assert_eq!(computer.instruction_count(), 0);
+        computer.provide_input(Some(7));
+        assert_eq!(computer.run_with_budget(2).map(Action::output), Some(7));
+        assert!(computer.run_with_budget(0).is_none());
+        assert!(matches!(computer.run_with_budget(1), Some(Action::Halt)));
+        assert_eq!(computer.instruction_count(), 3);
+    }
+
+    #[test]
+    fn recover_after_fault() {
+        let mut computer = Computer::new(vec![4, -1, 99]);
+        assert!(computer.try_run().is_err());
+        computer.poke(1, 2).unwrap();
+        assert_eq!(computer.try_run().unwrap().output(), 99);
+        assert!(matches!(computer.try_run(), Ok(Action::Halt)));
+    }
 }
@@ -0,0 +1,268 @@
+//! Intcode memory: a dense region holding the program image and anything close to it, and a
+//! sparse map of pages for addresses far past the end of the program.
+//!
+//! Both regions are stored in pages that are shared between clones, and only copied when a clone
+//! writes to them. Cloning a computer is therefore cheap, and a clone that runs a short query only
+//! pays for the pages it writes to.
+
+use crate::Fault;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::sync::Arc;
+
+/// Number of cells in each sparse page.
+pub const PAGE_SIZE: usize = 1024;
+
+/// Number of cells in each page of the dense region. Programs are small, so these are kept small
+/// too, so that a write does not copy much more than the program image would be.
+const CHUNK_SIZE: usize = 128;
+
+/// Addresses below this are stored densely, even if the program image is smaller.
+pub const DENSE_SIZE: usize = 1 << 16;
+
+type Page = [i64; PAGE_SIZE];
+type Chunk = [i64; CHUNK_SIZE];
+
+#[derive(Debug, Clone)]
+pub struct Memory {
+    dense: Vec<Arc<Chunk>>,
+    /// Number of allocated cells in the dense region. The rest of its last page is zero.
+    len: usize,
+    pages: HashMap<usize, Arc<Page>>,
+    dense_size: usize,
+    limit: Option<usize>,
+}
+
+impl Memory {
+    pub fn new(image: Vec<i64>) -> Memory {
+        let dense = image
+            .chunks(CHUNK_SIZE)
+            .map(|cells| {
+                let mut chunk = [0; CHUNK_SIZE];
+                chunk[..cells.len()].copy_from_slice(cells);
+                Arc::new(chunk)
+            })
+            .collect();
+
+        Memory {
+            dense_size: image.len().max(DENSE_SIZE),
+            dense,
+            len: image.len(),
+            pages: HashMap::new(),
+            limit: None,
+        }
+    }
+
+    /// Limit the number of cells that may be allocated. Writes that would need more memory fail
+    /// with [`Fault::OutOfMemory`].
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Number of cells currently allocated.
+    pub fn allocated(&self) -> usize {
+        self.len + self.pages.len() * PAGE_SIZE
+    }
+
+    /// Number of cells in the densely stored memory, which starts at address 0.
+    pub fn dense_len(&self) -> usize {
+        self.len
+    }
+
+    /// The densely stored memory, in consecutive slices starting at address 0.
+    pub fn dense_chunks(&self) -> impl Iterator<Item = &[i64]> {
+        let len = self.len;
+        self.dense
+            .iter()
+            .enumerate()
+            .map(move |(i, chunk)| &chunk[..(len - i * CHUNK_SIZE).min(CHUNK_SIZE)])
+    }
+
+    /// A copy of the densely stored memory, for callers that need it as a single slice.
+    pub fn dense_to_vec(&self) -> Vec<i64> {
+        let mut dense = Vec::with_capacity(self.len);
+        for chunk in self.dense_chunks() {
+            dense.extend_from_slice(chunk);
+        }
+        dense
+    }
+
+    /// Allocated sparse pages, as pairs of their first address and their contents.
+    pub fn pages(&self) -> impl Iterator<Item = (usize, &[i64])> {
+        self.pages
+            .iter()
+            .map(|(page, cells)| (page * PAGE_SIZE, &cells[..]))
+    }
+
+    /// Add a sparse page starting at `start`, such as one restored from a snapshot. Returns `false`
+    /// if the memory would not store those addresses in a page of their own, or already does.
+    pub fn insert_page(&mut self, start: usize, cells: Vec<i64>) -> bool {
+        let cells: Page = match cells.try_into() {
+            Ok(cells) => cells,
+            Err(_) => return false,
+        };
+        let page = start / PAGE_SIZE;
+        if !start.is_multiple_of(PAGE_SIZE)
+            || start.saturating_add(PAGE_SIZE) <= self.dense_size
+            || self.pages.contains_key(&page)
+        {
+            return false;
+        }
+        self.pages.insert(page, Arc::new(cells));
+        true
+    }
+
+    /// Number of cells in pages that are not shared with any other memory.
+    pub fn unshared(&self) -> usize {
+        let dense = self.dense.iter().map(Arc::strong_count);
+        let sparse = self.pages.values().map(Arc::strong_count);
+        dense.filter(|&count| count == 1).count() * CHUNK_SIZE
+            + sparse.filter(|&count| count == 1).count() * PAGE_SIZE
+    }
+
+    pub fn get(&self, address: usize) -> i64 {
+        if address < self.dense_size {
+            let (chunk, offset) = (address / CHUNK_SIZE, address % CHUNK_SIZE);
+            self.dense.get(chunk).map_or(0, |cells| cells[offset])
+        } else {
+            let (page, offset) = (address / PAGE_SIZE, address % PAGE_SIZE);
+            self.pages.get(&page).map_or(0, |cells| cells[offset])
+        }
+    }
+
+    /// Write a value, returning the value previously at the address.
+    pub fn set(&mut self, address: usize, value: i64) -> Result<i64, Fault> {
+        if let Some(cell) = self.cell_mut(address) {
+            return Ok(std::mem::replace(cell, value));
+        }
+
+        // Unallocated memory already reads as zero
+        if value == 0 {
+            return Ok(0);
+        }
+
+        self.allocate(address)?;
+        let cell = self.cell_mut(address).expect("memory was just allocated");
+        Ok(std::mem::replace(cell, value))
+    }
+
+    /// Allocate whatever writing the value to the address would, so that the write can not fail.
+    pub fn reserve(&mut self, address: usize, value: i64) -> Result<(), Fault> {
+        let allocated = if address < self.dense_size {
+            address < self.len
+        } else {
+            self.pages.contains_key(&(address / PAGE_SIZE))
+        };
+        if allocated || value == 0 {
+            return Ok(());
+        }
+        self.allocate(address)
+    }
+
+    /// The cell at an allocated address, copying its page first if it is shared.
+    fn cell_mut(&mut self, address: usize) -> Option<&mut i64> {
+        if address < self.dense_size {
+            if address >= self.len {
+                return None;
+            }
+            let (chunk, offset) = (address / CHUNK_SIZE, address % CHUNK_SIZE);
+            Some(&mut Arc::make_mut(&mut self.dense[chunk])[offset])
+        } else {
+            let (page, offset) = (address / PAGE_SIZE, address % PAGE_SIZE);
+            let cells = self.pages.get_mut(&page)?;
+            Some(&mut Arc::make_mut(cells)[offset])
+        }
+    }
+
+    fn allocate(&mut self, address: usize) -> Result<(), Fault> {
+        let extra = if address < self.dense_size {
+            address + 1 - self.len
+        } else {
+            PAGE_SIZE
+        };
+
+        if let Some(limit) = self.limit {
+            if self.allocated() + extra > limit {
+                return Err(Fault::OutOfMemory(address));
+            }
+        }
+
+        if address < self.dense_size {
+            let chunks = address / CHUNK_SIZE + 1;
+            while self.dense.len() < chunks {
+                self.dense.push(Arc::new([0; CHUNK_SIZE]));
+            }
+            self.len = address + 1;
+        } else {
+            self.pages
+                .insert(address / PAGE_SIZE, Arc::new([0; PAGE_SIZE]));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Computer;
+
+    #[test]
+    fn far_addresses_are_sparse() {
+        let mut memory = Memory::new(vec![1, 2, 3]);
+        assert_eq!(memory.get(1_000_000_000_000), 0);
+        assert_eq!(memory.set(1_000_000_000_000, 5), Ok(0));
+        assert_eq!(memory.set(1_000_000_000_001, 6), Ok(0));
+        assert_eq!(memory.get(1_000_000_000_000), 5);
+        assert_eq!(memory.get(1_000_000_000_001), 6);
+        assert_eq!(memory.allocated(), 3 + PAGE_SIZE);
+
+        assert_eq!(memory.set(10, 7), Ok(0));
+        assert_eq!(memory.dense_to_vec(), vec![1, 2, 3, 0, 0, 0, 0, 0, 0, 0, 7]);
+
+        let mut memory = Memory::new(vec![1; 300]);
+        let lengths = memory.dense_chunks().map(<[i64]>::len).collect::<Vec<_>>();
+        assert_eq!(lengths, vec![CHUNK_SIZE, CHUNK_SIZE, 300 - 2 * CHUNK_SIZE]);
+        memory.set(400, 2).unwrap();
+        assert_eq!(memory.dense_len(), 401);
+        assert_eq!(memory.dense_chunks().flatten().sum::<i64>(), 302);
+    }
+
+    #[test]
+    fn clones_share_pages() {
+        let mut memory = Memory::new(vec![1; 1000]);
+        memory.set(1_000_000_000_000, 5).unwrap();
+        assert_eq!(memory.unshared(), 1024 + PAGE_SIZE);
+
+        let mut clone = memory.clone();
+        assert_eq!(clone.unshared(), 0);
+        assert_eq!(clone.set(500, 2), Ok(1));
+        assert_eq!(clone.set(1_000_000_000_000, 6), Ok(5));
+        assert_eq!(clone.unshared(), CHUNK_SIZE + PAGE_SIZE);
+        assert_eq!(memory.unshared(), CHUNK_SIZE + PAGE_SIZE);
+
+        assert_eq!(memory.get(500), 1);
+        assert_eq!(memory.get(1_000_000_000_000), 5);
+        assert_eq!(clone.get(500), 2);
+        assert_eq!(clone.get(1_000_000_000_000), 6);
+    }
+
+    #[test]
+    fn memory_limit() {
+        // Write to address 10^12
+        let mut computer = Computer::new(vec![1101, 1, 2, 1_000_000_000_000, 99]);
+        computer.set_memory_limit(Some(1000));
+
+        let error = computer.try_run().unwrap_err();
+        assert_eq!(error.fault, Fault::OutOfMemory(1_000_000_000_000));
+        assert_eq!(error.instruction, 0);
+
+        computer.set_memory_limit(None);
+        assert!(computer.try_run().is_ok());
+        assert_eq!(computer.peek(1_000_000_000_000), 3);
+    }
+}
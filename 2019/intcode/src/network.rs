@@ -0,0 +1,369 @@
+//! A network of computers that exchange packets, as in day 23.
+//!
+//! Every node runs the same program and receives its address as its first input. A node sends a
+//! packet by outputting the destination address followed by `x` and `y`, and reads packets by
+//! requesting input, receiving `-1` if its queue is empty. Nodes are scheduled round-robin in
+//! order of their address, so a run is fully deterministic.
+
+use crate::{Action, Computer, IntcodeError};
+use std::collections::VecDeque;
+use std::fmt;
+
+pub struct Network {
+    nodes: Vec<Node>,
+    idle_polls: usize,
+}
+
+struct Node {
+    computer: Computer,
+    queue: VecDeque<Packet>,
+    partial: Vec<i64>,
+    stats: NodeStats,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Packet {
+    pub source: i64,
+    pub destination: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct NodeStats {
+    pub sent: usize,
+    pub received: usize,
+    /// Number of times the node asked for input while its queue was empty.
+    pub idle_polls: usize,
+}
+
+/// Decides what happens to packets sent to addresses outside the network, and what to do when
+/// the network goes idle. Packets pushed to `outbox` are delivered as if they had been sent by a
+/// node. Returning `Some` stops the network.
+pub trait Router {
+    type Output;
+
+    fn route(&mut self, packet: Packet, outbox: &mut Vec<Packet>) -> Option<Self::Output>;
+
+    /// Called when no node has sent or received a packet during a full round. If the router does
+    /// not send any packets the network is deadlocked.
+    fn idle(&mut self, _outbox: &mut Vec<Packet>) -> Option<Self::Output> {
+        None
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NetworkError {
+    Fault {
+        node: usize,
+        error: IntcodeError,
+    },
+    /// The network is idle and the router did not send any packets.
+    Deadlock,
+    /// Every node has halted.
+    Halted,
+}
+
+impl Network {
+    /// Create a network of `size` nodes, each running a copy of `program`.
+    pub fn new(program: &Computer, size: usize) -> Network {
+        let nodes = (0..size)
+            .map(|address| {
+                let mut computer = program.clone();
+                computer.provide_input(Some(address as i64));
+                Node {
+                    computer,
+                    queue: VecDeque::new(),
+                    partial: Vec::new(),
+                    stats: NodeStats::default(),
+                }
+            })
+            .collect();
+
+        Network {
+            nodes,
+            idle_polls: 2,
+        }
+    }
+
+    /// Set how many times a node may poll an empty queue before its turn ends.
+    pub fn set_idle_polls(&mut self, idle_polls: usize) {
+        self.idle_polls = idle_polls.max(1);
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, address: usize) -> &Computer {
+        &self.nodes[address].computer
+    }
+
+    pub fn stats(&self, address: usize) -> NodeStats {
+        self.nodes[address].stats
+    }
+
+    /// Queue a packet for delivery, as if it had been sent from `packet.source`.
+    pub fn send(&mut self, packet: Packet) -> bool {
+        match self.address(packet.destination) {
+            Some(address) => {
+                self.nodes[address].queue.push_back(packet);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn run<R: Router>(&mut self, router: &mut R) -> Result<R::Output, NetworkError> {
+        loop {
+            let mut active = false;
+
+            for address in 0..self.nodes.len() {
+                let (node_active, outbox) = self.run_node(address)?;
+                active |= node_active;
+
+                if let Some(output) = self.deliver(outbox, router) {
+                    return Ok(output);
+                }
+            }
+
+            if self.nodes.iter().all(|node| node.computer.is_halted()) {
+                return Err(NetworkError::Halted);
+            }
+
+            let queued = self.nodes.iter().any(|node| !node.queue.is_empty());
+            if !active && !queued {
+                let mut outbox = Vec::new();
+                if let Some(output) = router.idle(&mut outbox) {
+                    return Ok(output);
+                }
+                if outbox.is_empty() {
+                    return Err(NetworkError::Deadlock);
+                }
+                if let Some(output) = self.deliver(outbox, router) {
+                    return Ok(output);
+                }
+            }
+        }
+    }
+
+    /// Run a node until it has polled an empty queue enough times, returning whether it sent or
+    /// received any packets, and the packets it sent.
+    fn run_node(&mut self, address: usize) -> Result<(bool, Vec<Packet>), NetworkError> {
+        let idle_polls = self.idle_polls;
+        let node = &mut self.nodes[address];
+
+        let mut active = false;
+        let mut polls = 0;
+        let mut outbox = Vec::new();
+
+        loop {
+            let action = node
+                .computer
+                .try_run()
+                .map_err(|error| NetworkError::Fault {
+                    node: address,
+                    error,
+                })?;
+
+            match action {
+                Action::Halt => break,
+                Action::NeedsInput => match node.queue.pop_front() {
+                    Some(packet) => {
+                        active = true;
+                        node.stats.received += 1;
+                        node.computer.provide_input(vec![packet.x, packet.y]);
+                    }
+                    None => {
+                        if polls == idle_polls {
+                            break;
+                        }
+                        polls += 1;
+                        node.stats.idle_polls += 1;
+                        node.computer.provide_input(Some(-1));
+                    }
+                },
+                Action::Output(value) => {
+                    node.partial.push(value);
+                    if let [destination, x, y] = node.partial[..] {
+                        active = true;
+                        node.partial.clear();
+                        node.stats.sent += 1;
+                        outbox.push(Packet {
+                            source: address as i64,
+                            destination,
+                            x,
+                            y,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok((active, outbox))
+    }
+
+    fn deliver<R: Router>(&mut self, mut outbox: Vec<Packet>, router: &mut R) -> Option<R::Output> {
+        let mut pending = outbox.drain(..).collect::<VecDeque<_>>();
+
+        while let Some(packet) = pending.pop_front() {
+            if !self.send(packet) {
+                let output = router.route(packet, &mut outbox);
+                pending.extend(outbox.drain(..));
+                if output.is_some() {
+                    return output;
+                }
+            }
+        }
+
+        None
+    }
+
+    fn address(&self, destination: i64) -> Option<usize> {
+        if 0 <= destination && (destination as usize) < self.nodes.len() {
+            Some(destination as usize)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetworkError::Fault { node, error } => write!(f, "Node {}: {}", node, error),
+            NetworkError::Deadlock => write!(f, "Network is deadlocked"),
+            NetworkError::Halted => write!(f, "Every node has halted"),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::program;
+
+    /// Every node forwards a counter to the next node, incrementing it. The last node sends the
+    /// counter to address 255.
+    const RING: &str = "
+                in [address]
+                add [address], #1, [next]
+                jez [address], #start
+        wait:   in [x]
+                seq [x], #-1, [empty]
+                jnz [empty], #wait
+                in [y]
+                jnz #1, #send
+        start:  add #0, #0, [x]
+                add #0, #0, [y]
+        send:   seq [next], #size, [last]
+                jez [last], #forward
+                add #255, #0, [next]
+        forward:
+                add [y], #1, [y]
+                out [next]
+                out [x]
+                out [y]
+                jnz #1, #wait
+        address: data 0
+        next:   data 0
+        x:      data 0
+        y:      data 0
+        empty:  data 0
+        last:   data 0
+    ";
+
+    struct Sink;
+
+    impl Router for Sink {
+        type Output = Packet;
+
+        fn route(&mut self, packet: Packet, _outbox: &mut Vec<Packet>) -> Option<Packet> {
+            Some(packet)
+        }
+    }
+
+    fn ring(size: i64) -> Computer {
+        program(&RING.replace("#size", &format!("#{}", size)))
+    }
+
+    #[test]
+    fn forward_around_ring() {
+        let mut network = Network::new(&ring(5), 5);
+        let packet = network.run(&mut Sink).unwrap();
+        assert_eq!(
+            packet,
+            Packet {
+                source: 4,
+                destination: 255,
+                x: 0,
+                y: 5
+            }
+        );
+
+        assert_eq!(network.stats(0).sent, 1);
+        assert_eq!(network.stats(0).received, 0);
+        assert_eq!(network.stats(4).received, 1);
+    }
+
+    struct Nat {
+        last: Option<Packet>,
+        restarts: usize,
+    }
+
+    impl Router for Nat {
+        type Output = usize;
+
+        fn route(&mut self, packet: Packet, _outbox: &mut Vec<Packet>) -> Option<usize> {
+            self.last = Some(packet);
+            None
+        }
+
+        fn idle(&mut self, outbox: &mut Vec<Packet>) -> Option<usize> {
+            let packet = self.last.take()?;
+            if packet.y >= 15 {
+                return Some(self.restarts);
+            }
+            self.restarts += 1;
+            outbox.push(Packet {
+                source: 255,
+                destination: 0,
+                ..packet
+            });
+            None
+        }
+    }
+
+    #[test]
+    fn restart_when_idle() {
+        let mut network = Network::new(&ring(5), 5);
+        let mut nat = Nat {
+            last: None,
+            restarts: 0,
+        };
+        assert_eq!(network.run(&mut nat), Ok(2));
+        assert_eq!(network.stats(0).received, 2);
+    }
+
+    #[test]
+    fn deadlock() {
+        let mut network = Network::new(&ring(5), 5);
+        assert_eq!(network.run(&mut DropAll), Err(NetworkError::Deadlock));
+    }
+
+    struct DropAll;
+
+    impl Router for DropAll {
+        type Output = ();
+
+        fn route(&mut self, _packet: Packet, _outbox: &mut Vec<Packet>) -> Option<()> {
+            None
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::Instruction;
+
+/// Hooks that are invoked while a [`Computer`](crate::Computer) executes. Every method has an
+/// empty default implementation, and `()` implements the trait without doing anything, so
+/// running without an observer compiles down to the plain interpreter.
+pub trait Observer {
+    /// Called after an instruction has been decoded, but before it is executed.
+    fn instruction(&mut self, _address: usize, _instruction: Instruction) {}
+
+    /// Called when an instruction reads a parameter from memory.
+    fn read(&mut self, _address: usize, _value: i64) {}
+
+    /// Called when an instruction writes to memory.
+    fn write(&mut self, _address: usize, _old: i64, _new: i64) {}
+
+    /// Called when an input instruction consumes a value.
+    fn input(&mut self, _value: i64) {}
+
+    /// Called when an output instruction produces a value.
+    fn output(&mut self, _value: i64) {}
+}
+
+impl Observer for () {}
+
+impl<O: Observer + ?Sized> Observer for &mut O {
+    fn instruction(&mut self, address: usize, instruction: Instruction) {
+        (**self).instruction(address, instruction)
+    }
+
+    fn read(&mut self, address: usize, value: i64) {
+        (**self).read(address, value)
+    }
+
+    fn write(&mut self, address: usize, old: i64, new: i64) {
+        (**self).write(address, old, new)
+    }
+
+    fn input(&mut self, value: i64) {
+        (**self).input(value)
+    }
+
+    fn output(&mut self, value: i64) {
+        (**self).output(value)
+    }
+}
+
+impl<O: Observer + ?Sized> Observer for Box<O> {
+    fn instruction(&mut self, address: usize, instruction: Instruction) {
+        (**self).instruction(address, instruction)
+    }
+
+    fn read(&mut self, address: usize, value: i64) {
+        (**self).read(address, value)
+    }
+
+    fn write(&mut self, address: usize, old: i64, new: i64) {
+        (**self).write(address, old, new)
+    }
+
+    fn input(&mut self, value: i64) {
+        (**self).input(value)
+    }
+
+    fn output(&mut self, value: i64) {
+        (**self).output(value)
+    }
+}
+
+/// An observer that may not be there.
+impl<O: Observer> Observer for Option<O> {
+    fn instruction(&mut self, address: usize, instruction: Instruction) {
+        if let Some(observer) = self {
+            observer.instruction(address, instruction)
+        }
+    }
+
+    fn read(&mut self, address: usize, value: i64) {
+        if let Some(observer) = self {
+            observer.read(address, value)
+        }
+    }
+
+    fn write(&mut self, address: usize, old: i64, new: i64) {
+        if let Some(observer) = self {
+            observer.write(address, old, new)
+        }
+    }
+
+    fn input(&mut self, value: i64) {
+        if let Some(observer) = self {
+            observer.input(value)
+        }
+    }
+
+    fn output(&mut self, value: i64) {
+        if let Some(observer) = self {
+            observer.output(value)
+        }
+    }
+}
+
+/// Forwards every event to two observers.
+pub(crate) struct Pair<A, B>(pub A, pub B);
+
+impl<A: Observer, B: Observer> Observer for Pair<A, B> {
+    fn instruction(&mut self, address: usize, instruction: Instruction) {
+        self.0.instruction(address, instruction);
+        self.1.instruction(address, instruction);
+    }
+
+    fn read(&mut self, address: usize, value: i64) {
+        self.0.read(address, value);
+        self.1.read(address, value);
+    }
+
+    fn write(&mut self, address: usize, old: i64, new: i64) {
+        self.0.write(address, old, new);
+        self.1.write(address, old, new);
+    }
+
+    fn input(&mut self, value: i64) {
+        self.0.input(value);
+        self.1.input(value);
+    }
+
+    fn output(&mut self, value: i64) {
+        self.0.output(value);
+        self.1.output(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Computer;
+
+    #[derive(Default)]
+    struct Trace {
+        events: Vec<String>,
+    }
+
+    impl Observer for Trace {
+        fn instruction(&mut self, address: usize, instruction: Instruction) {
+            self.events.push(format!("{}: {}", address, instruction));
+        }
+
+        fn read(&mut self, address: usize, value: i64) {
+            self.events.push(format!("read [{}] = {}", address, value));
+        }
+
+        fn write(&mut self, address: usize, old: i64, new: i64) {
+            self.events
+                .push(format!("write [{}] {} -> {}", address, old, new));
+        }
+
+        fn input(&mut self, value: i64) {
+            self.events.push(format!("input {}", value));
+        }
+
+        fn output(&mut self, value: i64) {
+            self.events.push(format!("output {}", value));
+        }
+    }
+
+    #[test]
+    fn trace() {
+        let mut computer = Computer::new(vec![3, 9, 1002, 9, 3, 9, 4, 9, 99, 0]);
+        computer.provide_input(Some(5));
+
+        let mut trace = Trace::default();
+        assert_eq!(computer.run_with(&mut trace).output(), 15);
+        assert_eq!(
+            trace.events,
+            vec![
+                "0: in [9]",
+                "input 5",
+                "write [9] 0 -> 5",
+                "2: mul [9], #3, [9]",
+                "read [9] = 5",
+                "write [9] 5 -> 15",
+                "6: out [9]",
+                "read [9] = 15",
+                "output 15",
+            ]
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Chains of computers where each stage feeds its outputs into the input of the next, such as the
+//! amplifiers from day 7.
+
+use crate::{Action, Computer, IntcodeError};
+use std::fmt;
+
+pub struct Pipeline {
+    stages: Vec<Computer>,
+    feedback: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PipelineError {
+    Fault {
+        stage: usize,
+        error: IntcodeError,
+    },
+    /// Every stage that has not halted is waiting for input that will never arrive.
+    Deadlock,
+}
+
+impl Pipeline {
+    pub fn new(stages: Vec<Computer>) -> Pipeline {
+        Pipeline {
+            stages,
+            feedback: false,
+        }
+    }
+
+    /// Create a pipeline where each stage runs a copy of `program`, and receives its phase as its
+    /// first input.
+    pub fn with_phases(program: &Computer, phases: &[i64]) -> Pipeline {
+        let stages = phases
+            .iter()
+            .map(|&phase| {
+                let mut stage = program.clone();
+                stage.provide_input(Some(phase));
+                stage
+            })
+            .collect();
+        Pipeline::new(stages)
+    }
+
+    /// Also send the outputs of the last stage back into the first stage.
+    pub fn set_feedback(&mut self, feedback: bool) {
+        self.feedback = feedback;
+    }
+
+    pub fn stages(&self) -> &[Computer] {
+        &self.stages
+    }
+
+    pub fn provide_input(&mut self, stage: usize, input: impl IntoIterator<Item = i64>) {
+        self.stages[stage].provide_input(input);
+    }
+
+    /// Send `input` to the first stage and run every stage until they have all halted. Returns
+    /// every value output by the last stage.
+    pub fn run(&mut self, input: impl IntoIterator<Item = i64>) -> Result<Vec<i64>, PipelineError> {
+        let mut outputs = Vec::new();
+
+        let count = self.stages.len();
+        if count == 0 {
+            outputs.extend(input);
+            return Ok(outputs);
+        }
+
+        self.stages[0].provide_input(input);
+
+        loop {
+            let mut progress = false;
+
+            for index in 0..count {
+                loop {
+                    let action =
+                        self.stages[index]
+                            .try_run()
+                            .map_err(|error| PipelineError::Fault {
+                                stage: index,
+                                error,
+                            })?;
+
+                    let value = match action {
+                        Action::Output(value) => value,
+                        Action::NeedsInput | Action::Halt => break,
+                    };
+
+                    progress = true;
+
+                    if index + 1 < count {
+                        self.stages[index + 1].provide_input(Some(value));
+                    } else {
+                        outputs.push(value);
+                        if self.feedback {
+                            self.stages[0].provide_input(Some(value));
+                        }
+                    }
+                }
+            }
+
+            if self.stages.iter().all(Computer::is_halted) {
+                return Ok(outputs);
+            }
+
+            if !progress {
+                return Err(PipelineError::Deadlock);
+            }
+        }
+    }
+}
+
+/// Try every permutation of `phases`, sending `0` to the first stage, and return the permutation
+/// for which the last stage output the greatest final value, together with that value.
+pub fn best_phases(
+    program: &Computer,
+    phases: &[i64],
+    feedback: bool,
+) -> Result<Option<(Vec<i64>, i64)>, PipelineError> {
+    let mut best: Option<(Vec<i64>, i64)> = None;
+
+    for permutation in permutations(phases) {
+        let mut pipeline = Pipeline::with_phases(program, &permutation);
+        pipeline.set_feedback(feedback);
+
+        if let Some(&signal) = pipeline.run(Some(0))?.last() {
+            let better = match &best {
+                Some((_, max)) => signal > *max,
+                None => true,
+            };
+            if better {
+                best = Some((permutation, signal));
+            }
+        }
+    }
+
+    Ok(best)
+}
+
+/// All orderings of `values`, generated with Heap's algorithm.
+pub fn permutations(values: &[i64]) -> Vec<Vec<i64>> {
+    let mut values = values.to_vec();
+    let mut permutations = vec![values.clone()];
+
+    let mut counters = vec![0; values.len()];
+    let mut i = 1;
+    while i < values.len() {
+        if counters[i] < i {
+            let j = if i % 2 == 0 { 0 } else { counters[i] };
+            values.swap(j, i);
+            permutations.push(values.clone());
+            counters[i] += 1;
+            i = 1;
+        } else {
+            counters[i] = 0;
+            i += 1;
+        }
+    }
+
+    permutations
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PipelineError::Fault { stage, error } => write!(f, "Stage {}: {}", stage, error),
+            PipelineError::Deadlock => write!(f, "Pipeline is deadlocked"),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serial_amplifiers() {
+        // Example from day 7, part 1
+        let program = Computer::new(vec![
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ]);
+
+        let mut pipeline = Pipeline::with_phases(&program, &[4, 3, 2, 1, 0]);
+        assert_eq!(pipeline.run(Some(0)), Ok(vec![43210]));
+
+        let best = best_phases(&program, &[0, 1, 2, 3, 4], false).unwrap();
+        assert_eq!(best, Some((vec![4, 3, 2, 1, 0], 43210)));
+    }
+
+    #[test]
+    fn feedback_loop() {
+        // Example from day 7, part 2
+        let program = Computer::new(vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ]);
+
+        let best = best_phases(&program, &[5, 6, 7, 8, 9], true).unwrap();
+        assert_eq!(best, Some((vec![9, 8, 7, 6, 5], 139629729)));
+    }
+
+    #[test]
+    fn deadlock() {
+        // Every stage needs three inputs, but only receives two
+        let program = Computer::new(vec![3, 0, 3, 0, 3, 0, 4, 0, 99]);
+        let mut pipeline = Pipeline::with_phases(&program, &[1, 2]);
+        assert_eq!(pipeline.run(Some(0)), Err(PipelineError::Deadlock));
+    }
+
+    #[test]
+    fn all_permutations() {
+        let mut permutations = permutations(&[1, 2, 3]);
+        permutations.sort();
+        assert_eq!(
+            permutations,
+            vec![
+                vec![1, 2, 3],
+                vec![1, 3, 2],
+                vec![2, 1, 3],
+                vec![2, 3, 1],
+                vec![3, 1, 2],
+                vec![3, 2, 1],
+            ]
+        );
+    }
+}
@@ -0,0 +1,355 @@
+//! Counting where a program spends its time.
+//!
+//! Enable profiling with [`Computer::set_profiling`] and every instruction executed from then on
+//! is counted, together with every read and write of memory. Since computers are usually cloned
+//! from a single program, enabling it before creating a [`Network`](crate::network::Network) or
+//! [`Pipeline`](crate::pipeline::Pipeline) profiles every node.
+
+use crate::memory::DENSE_SIZE;
+use crate::{Computer, Instruction, Observer};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Number of loops and addresses listed in a report.
+const REPORT_SIZE: usize = 10;
+
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    hits: Counter,
+    reads: Counter,
+    writes: Counter,
+    opcodes: [u64; 10],
+    /// Taken jumps that go backwards, by their source and target.
+    backward: HashMap<(usize, usize), u64>,
+    /// The previous instruction, if it was a jump.
+    jump: Option<usize>,
+    /// The instruction being executed, which is counted once it completes.
+    current: Option<(usize, Instruction)>,
+}
+
+/// A loop formed by a jump from `end` back to `start`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Loop {
+    pub start: usize,
+    /// Address of the jump that closes the loop.
+    pub end: usize,
+    /// Number of times the jump was taken.
+    pub iterations: u64,
+    /// Number of instructions executed inside the loop, including nested loops.
+    pub instructions: u64,
+}
+
+/// Counts stored densely for small addresses, and sparsely for the rest.
+#[derive(Debug, Clone, Default)]
+struct Counter {
+    dense: Vec<u64>,
+    sparse: HashMap<usize, u64>,
+}
+
+impl Counter {
+    fn increment(&mut self, address: usize) {
+        if address < DENSE_SIZE {
+            if address >= self.dense.len() {
+                self.dense.resize(address + 1, 0);
+            }
+            self.dense[address] += 1;
+        } else {
+            *self.sparse.entry(address).or_insert(0) += 1;
+        }
+    }
+
+    fn get(&self, address: usize) -> u64 {
+        match self.dense.get(address) {
+            Some(&count) => count,
+            None => self.sparse.get(&address).copied().unwrap_or(0),
+        }
+    }
+
+    fn sum(&self, start: usize, end: usize) -> u64 {
+        let dense_end = self.dense.len().min(end.saturating_add(1));
+        let dense = self
+            .dense
+            .get(start..dense_end)
+            .map_or(0, |counts| counts.iter().sum());
+        let sparse = self
+            .sparse
+            .iter()
+            .filter(|&(&address, _)| (start..=end).contains(&address))
+            .map(|(_, &count)| count)
+            .sum::<u64>();
+        dense + sparse
+    }
+
+    fn nonzero(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        let dense = self.dense.iter().copied().enumerate();
+        let sparse = self
+            .sparse
+            .iter()
+            .map(|(&address, &count)| (address, count));
+        dense.chain(sparse).filter(|&(_, count)| count != 0)
+    }
+}
+
+impl Profile {
+    /// Number of times the instruction at `address` was executed. Instructions are counted when
+    /// they complete, so an instruction that waits for input is only counted once.
+    pub fn hits(&self, address: usize) -> u64 {
+        self.hits.get(address)
+    }
+
+    pub fn reads(&self, address: usize) -> u64 {
+        self.reads.get(address)
+    }
+
+    pub fn writes(&self, address: usize) -> u64 {
+        self.writes.get(address)
+    }
+
+    /// Every executed address with its hit count, most executed first.
+    pub fn hot_addresses(&self) -> Vec<(usize, u64)> {
+        let mut addresses = self.hits.nonzero().collect::<Vec<_>>();
+        addresses.sort_by_key(|&(address, count)| (std::cmp::Reverse(count), address));
+        addresses
+    }
+
+    /// How many times each kind of instruction was executed, by mnemonic, most executed first.
+    pub fn instruction_mix(&self) -> Vec<(&'static str, u64)> {
+        let mut mix = self
+            .opcodes
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count != 0)
+            .map(|(opcode, &count)| (mnemonic(opcode), count))
+            .collect::<Vec<_>>();
+        mix.sort_by_key(|&(mnemonic, count)| (std::cmp::Reverse(count), mnemonic));
+        mix
+    }
+
+    /// Loops found from the backward jumps that were taken, with the ones that executed the most
+    /// instructions first.
+    pub fn hot_loops(&self) -> Vec<Loop> {
+        let mut loops = self
+            .backward
+            .iter()
+            .map(|(&(end, start), &iterations)| Loop {
+                start,
+                end,
+                iterations,
+                instructions: self.hits.sum(start, end),
+            })
+            .collect::<Vec<_>>();
+        loops.sort_by_key(|l| (std::cmp::Reverse(l.instructions), l.start, l.end));
+        loops
+    }
+
+    pub fn total_instructions(&self) -> u64 {
+        self.opcodes.iter().sum()
+    }
+
+    /// Count the current instruction, which completed.
+    pub(crate) fn commit(&mut self) {
+        let (address, instruction) = match self.current.take() {
+            Some(current) => current,
+            None => return,
+        };
+        self.hits.increment(address);
+        self.opcodes[slot(instruction)] += 1;
+        if let Some(jump) = self.jump {
+            if address <= jump {
+                *self.backward.entry((jump, address)).or_insert(0) += 1;
+            }
+        }
+        self.jump = match instruction {
+            Instruction::Jnz(..) | Instruction::Jez(..) => Some(address),
+            _ => None,
+        };
+    }
+}
+
+impl Observer for Profile {
+    fn instruction(&mut self, address: usize, instruction: Instruction) {
+        self.current = Some((address, instruction));
+    }
+
+    fn read(&mut self, address: usize, _value: i64) {
+        self.reads.increment(address);
+    }
+
+    fn write(&mut self, address: usize, _old: i64, _new: i64) {
+        self.writes.increment(address);
+    }
+}
+
+impl Computer {
+    /// Start or stop profiling. Starting discards any previous profile.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profile = if enabled { Some(Box::default()) } else { None };
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_deref()
+    }
+}
+
+/// Index into `Profile::opcodes`. `hlt` takes the unused slot 0.
+fn slot(instruction: Instruction) -> usize {
+    match instruction {
+        Instruction::Halt => 0,
+        _ => instruction.opcode() as usize,
+    }
+}
+
+fn mnemonic(slot: usize) -> &'static str {
+    match slot {
+        0 => "hlt",
+        1 => "add",
+        2 => "mul",
+        3 => "in",
+        4 => "out",
+        5 => "jnz",
+        6 => "jez",
+        7 => "slt",
+        8 => "seq",
+        _ => "rb",
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total = self.total_instructions().max(1);
+        let percent = |count: u64| 100.0 * count as f64 / total as f64;
+
+        writeln!(f, "{} instructions executed", self.total_instructions())?;
+
+        writeln!(f, "\nHot loops:")?;
+        for l in self.hot_loops().iter().take(REPORT_SIZE) {
+            writeln!(
+                f,
+                "{:>6}..{:<6} {:>12} iterations {:>14} instructions ({:.1}%)",
+                l.start,
+                l.end,
+                l.iterations,
+                l.instructions,
+                percent(l.instructions)
+            )?;
+        }
+
+        writeln!(f, "\nHot addresses:")?;
+        for (address, count) in self.hot_addresses().into_iter().take(REPORT_SIZE) {
+            writeln!(f, "{:>6} {:>14} ({:.1}%)", address, count, percent(count))?;
+        }
+
+        writeln!(f, "\nInstruction mix:")?;
+        for (mnemonic, count) in self.instruction_mix() {
+            writeln!(f, "{:>6} {:>14} ({:.1}%)", mnemonic, count, percent(count))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::program;
+    use crate::Action;
+
+    #[test]
+    fn nested_loops() {
+        let mut computer = program(
+            "
+                    add #0, #0, [i]
+            outer:  add #0, #0, [j]
+            inner:  add [j], #1, [j]
+                    slt [j], #3, [cond]
+                    jnz [cond], #inner
+                    add [i], #1, [i]
+                    slt [i], #2, [cond]
+                    jnz [cond], #outer
+                    hlt
+            i:      data 0
+            j:      data 0
+            cond:   data 0
+            ",
+        );
+        assert!(computer.profile().is_none());
+        computer.set_profiling(true);
+        computer.run();
+
+        let profile = computer.profile().unwrap();
+        assert_eq!(profile.hits(0), 1);
+        assert_eq!(profile.hits(8), 6);
+        assert_eq!(profile.hits(30), 1);
+        assert_eq!(profile.total_instructions(), 28);
+        assert_eq!(profile.total_instructions(), computer.instruction_count());
+        assert_eq!(profile.writes(33), 8);
+        assert_eq!(profile.reads(32), 12);
+
+        assert_eq!(
+            profile.hot_loops(),
+            vec![
+                Loop {
+                    start: 4,
+                    end: 27,
+                    iterations: 1,
+                    instructions: 26,
+                },
+                Loop {
+                    start: 8,
+                    end: 16,
+                    iterations: 4,
+                    instructions: 18,
+                },
+            ]
+        );
+
+        assert_eq!(profile.instruction_mix()[0], ("add", 11));
+        assert!(profile.to_string().contains("Hot loops:"));
+    }
+
+    #[test]
+    fn far_loop() {
+        const FAR: usize = 1_000_000_000_000;
+        let mut computer = program(
+            "
+                    add [n], #-1, [n]
+                    jez [n], #end
+                    jnz #1, #1000000000000
+            end:    hlt
+            n:      data 3
+            ",
+        );
+        // jnz #1, #0
+        for (offset, &value) in [1105, 1, 0].iter().enumerate() {
+            computer.poke(FAR + offset, value).unwrap();
+        }
+        computer.set_profiling(true);
+        computer.run();
+
+        let profile = computer.profile().unwrap();
+        assert_eq!(profile.hits(FAR), 2);
+        assert_eq!(
+            profile.hot_loops(),
+            vec![Loop {
+                start: 0,
+                end: FAR,
+                iterations: 2,
+                instructions: 11,
+            }]
+        );
+    }
+
+    #[test]
+    fn waiting_for_input() {
+        let mut computer = program("in [0]\nout [0]\nhlt");
+        computer.set_profiling(true);
+        assert_eq!(computer.run(), Action::NeedsInput);
+        assert_eq!(computer.run(), Action::NeedsInput);
+        computer.provide_input(Some(5));
+        assert_eq!(computer.run(), Action::Output(5));
+
+        let profile = computer.profile().unwrap();
+        assert_eq!(profile.hits(0), 1);
+        assert_eq!(profile.total_instructions(), computer.instruction_count());
+    }
+}
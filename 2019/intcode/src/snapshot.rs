@@ -0,0 +1,454 @@
+//! Saving and restoring the complete state of a [`Computer`].
+//!
+//! A snapshot starts with the magic bytes `ICSNAP` and a format version, followed by the
+//! instruction pointer, relative base, halted flag, pending input and memory. All integers are
+//! stored as zigzag-encoded LEB128 varints, which keeps typical Intcode memory small.
+//!
+//! Version 2 added sparse memory pages and the memory limit after the dense memory. Version 3
+//! added the arithmetic mode, the values that do not fit in 64 bits and the instruction set level
+//! after that. A wide value is stored as its address, sign and the 32-bit digits of its magnitude.
+//! Version 4 added the number of executed instructions at the end.
+//!
+//! Profiles, history, transcripts, watchpoints and the decode cache are not part of a snapshot.
+
+use crate::arith::BigInt;
+use crate::memory::PAGE_SIZE;
+use crate::{Arithmetic, Computer, Isa, Memory, Result};
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryInto;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+const MAGIC: &[u8] = b"ICSNAP";
+const VERSION: u64 = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    BadMagic,
+    UnsupportedVersion(u64),
+    Truncated,
+    Invalid,
+}
+
+impl Computer {
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        write_varint(&mut bytes, VERSION);
+        write_varint(&mut bytes, self.instruction as u64);
+        write_signed(&mut bytes, self.relative_base);
+        write_varint(&mut bytes, self.halted as u64);
+
+        write_varint(&mut bytes, self.input.len() as u64);
+        for &value in &self.input {
+            write_signed(&mut bytes, value);
+        }
+
+        write_varint(&mut bytes, self.memory.dense_len() as u64);
+        for &value in self.memory.dense_chunks().flatten() {
+            write_signed(&mut bytes, value);
+        }
+
+        let mut pages = self.memory.pages().collect::<Vec<_>>();
+        pages.sort_by_key(|&(start, _)| start);
+        write_varint(&mut bytes, pages.len() as u64);
+        for (start, cells) in pages {
+            write_varint(&mut bytes, start as u64);
+            for &value in cells {
+                write_signed(&mut bytes, value);
+            }
+        }
+
+        write_varint(
+            &mut bytes,
+            self.memory.limit().map_or(0, |limit| limit as u64 + 1),
+        );
+
+        write_varint(&mut bytes, arithmetic_code(self.arithmetic));
+        let mut wide = self.wide.iter().collect::<Vec<_>>();
+        wide.sort_by_key(|&(&address, _)| address);
+        write_varint(&mut bytes, wide.len() as u64);
+        for (&address, value) in wide {
+            let (negative, magnitude) = value.parts();
+            write_varint(&mut bytes, address as u64);
+            write_varint(&mut bytes, negative as u64);
+            write_varint(&mut bytes, magnitude.len() as u64);
+            for &digit in magnitude {
+                write_varint(&mut bytes, digit.into());
+            }
+        }
+        write_varint(&mut bytes, isa_code(self.isa));
+        write_varint(&mut bytes, self.executed);
+
+        bytes
+    }
+
+    pub fn from_snapshot(bytes: &[u8]) -> Result<Computer, SnapshotError> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(SnapshotError::BadMagic);
+        }
+
+        let mut reader = Reader {
+            bytes: &bytes[MAGIC.len()..],
+        };
+
+        let version = reader.varint()?;
+        if version == 0 || version > VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let instruction = reader.usize()?;
+        let relative_base = reader.signed()?;
+        let halted = match reader.varint()? {
+            0 => false,
+            1 => true,
+            _ => return Err(SnapshotError::Invalid),
+        };
+
+        let input_len = reader.usize()?;
+        let input = (0..input_len)
+            .map(|_| reader.signed())
+            .collect::<Result<VecDeque<_>, _>>()?;
+
+        let dense_len = reader.usize()?;
+        let dense = (0..dense_len)
+            .map(|_| reader.signed())
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut memory = Memory::new(dense);
+
+        if version >= 2 {
+            let page_count = reader.usize()?;
+            for _ in 0..page_count {
+                let start = reader.usize()?;
+                let cells = (0..PAGE_SIZE)
+                    .map(|_| reader.signed())
+                    .collect::<Result<Vec<_>, _>>()?;
+                if !memory.insert_page(start, cells) {
+                    return Err(SnapshotError::Invalid);
+                }
+            }
+
+            let limit = reader.usize()?;
+            memory.set_limit(limit.checked_sub(1));
+        }
+
+        let mut arithmetic = Arithmetic::default();
+        let mut wide = HashMap::new();
+        let mut isa = Isa::default();
+        if version >= 3 {
+            arithmetic = match reader.varint()? {
+                0 => Arithmetic::Wrapping,
+                1 => Arithmetic::Checked,
+                2 => Arithmetic::Big,
+                _ => return Err(SnapshotError::Invalid),
+            };
+
+            let wide_count = reader.usize()?;
+            for _ in 0..wide_count {
+                let address = reader.usize()?;
+                let negative = match reader.varint()? {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(SnapshotError::Invalid),
+                };
+                let digit_count = reader.usize()?;
+                let magnitude = (0..digit_count)
+                    .map(|_| {
+                        reader
+                            .varint()?
+                            .try_into()
+                            .map_err(|_| SnapshotError::Invalid)
+                    })
+                    .collect::<Result<Vec<u32>, _>>()?;
+                let value = BigInt::new(negative, magnitude);
+                // The memory holds the low bits of every wide value
+                if value.to_i64().is_some() || memory.get(address) != value.truncate() {
+                    return Err(SnapshotError::Invalid);
+                }
+                wide.insert(address, value);
+            }
+            if !wide.is_empty() && arithmetic != Arithmetic::Big {
+                return Err(SnapshotError::Invalid);
+            }
+
+            isa = match reader.varint()? {
+                0 => Isa::Day2,
+                1 => Isa::Day5,
+                2 => Isa::Full,
+                3 => Isa::Day5Part1,
+                _ => return Err(SnapshotError::Invalid),
+            };
+        }
+
+        let executed = if version >= 4 { reader.varint()? } else { 0 };
+
+        if !reader.bytes.is_empty() {
+            return Err(SnapshotError::Invalid);
+        }
+
+        Ok(Computer {
+            instruction,
+            memory,
+            input,
+            relative_base,
+            halted,
+            executed,
+            cache: None,
+            profile: None,
+            history: None,
+            transcript: None,
+            arithmetic,
+            wide,
+            isa,
+            watchpoints: Default::default(),
+        })
+    }
+
+    pub fn save_snapshot(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, self.snapshot())?;
+        Ok(())
+    }
+
+    pub fn load_snapshot(path: impl AsRef<Path>) -> Result<Computer> {
+        let bytes = fs::read(path)?;
+        Ok(Computer::from_snapshot(&bytes)?)
+    }
+}
+
+fn arithmetic_code(arithmetic: Arithmetic) -> u64 {
+    match arithmetic {
+        Arithmetic::Wrapping => 0,
+        Arithmetic::Checked => 1,
+        Arithmetic::Big => 2,
+    }
+}
+
+fn isa_code(isa: Isa) -> u64 {
+    match isa {
+        Isa::Day2 => 0,
+        Isa::Day5 => 1,
+        Isa::Full => 2,
+        Isa::Day5Part1 => 3,
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn varint(&mut self) -> Result<u64, SnapshotError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.bytes.split_first().ok_or(SnapshotError::Truncated)?;
+            self.bytes = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(SnapshotError::Invalid)
+    }
+
+    fn signed(&mut self) -> Result<i64, SnapshotError> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn usize(&mut self) -> Result<usize, SnapshotError> {
+        self.varint()?
+            .try_into()
+            .map_err(|_| SnapshotError::Invalid)
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn write_signed(bytes: &mut Vec<u8>, value: i64) {
+    write_varint(bytes, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::BadMagic => write!(f, "Not an intcode snapshot"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported snapshot version: {}", version)
+            }
+            SnapshotError::Truncated => write!(f, "Snapshot is truncated"),
+            SnapshotError::Invalid => write!(f, "Snapshot is corrupt"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Action;
+
+    #[test]
+    fn resume_from_snapshot() {
+        // Echo inputs back, doubled, forever
+        let code = vec![3, 100, 1002, 100, 2, 100, 4, 100, 1105, 1, 0];
+        let mut computer = Computer::new(code);
+        computer.provide_input(vec![i64::MIN / 2, 7, -3]);
+        assert_eq!(computer.run().output(), i64::MIN);
+
+        let snapshot = computer.snapshot();
+        let restored = Computer::from_snapshot(&snapshot).unwrap();
+
+        for computer in &mut [computer, restored.clone()] {
+            assert_eq!(computer.run().output(), 14);
+            assert_eq!(computer.run().output(), -6);
+            assert!(matches!(computer.run(), Action::NeedsInput));
+        }
+
+        assert_eq!(restored.peek(100), i64::MIN);
+        assert_eq!(restored.pending_input(), 2);
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let snapshot = Computer::new(vec![1, 2, 3, 99]).snapshot();
+
+        assert_eq!(
+            Computer::from_snapshot(b"hello").err(),
+            Some(SnapshotError::BadMagic)
+        );
+        assert_eq!(
+            Computer::from_snapshot(&snapshot[..snapshot.len() - 1]).err(),
+            Some(SnapshotError::Truncated)
+        );
+
+        let mut future = snapshot;
+        future[MAGIC.len()] = 5;
+        assert_eq!(
+            Computer::from_snapshot(&future).err(),
+            Some(SnapshotError::UnsupportedVersion(5))
+        );
+    }
+
+    #[test]
+    fn version_1() {
+        let mut bytes = MAGIC.to_vec();
+        for &value in &[1, 0, 0, 0, 0, 5] {
+            write_varint(&mut bytes, value);
+        }
+        for &value in &[104, -1, 104, 5, 99] {
+            write_signed(&mut bytes, value);
+        }
+
+        let mut computer = Computer::from_snapshot(&bytes).unwrap();
+        assert_eq!(computer.run().output(), -1);
+        assert_eq!(computer.run().output(), 5);
+    }
+
+    #[test]
+    fn version_2() {
+        let mut bytes = MAGIC.to_vec();
+        for &value in &[2, 0, 0, 0, 0, 3] {
+            write_varint(&mut bytes, value);
+        }
+        for &value in &[104, 7, 99] {
+            write_signed(&mut bytes, value);
+        }
+        write_varint(&mut bytes, 0);
+        write_varint(&mut bytes, 0);
+
+        let mut computer = Computer::from_snapshot(&bytes).unwrap();
+        assert_eq!(computer.arithmetic(), Arithmetic::Wrapping);
+        assert_eq!(computer.isa(), Isa::Full);
+        assert_eq!(computer.run().output(), 7);
+    }
+
+    #[test]
+    fn settings() {
+        let mut computer = Computer::new(vec![1, 0, 0, 0, 99]);
+        computer.set_arithmetic(Arithmetic::Checked);
+        computer.set_isa(Isa::Day2);
+        let restored = Computer::from_snapshot(&computer.snapshot()).unwrap();
+        assert_eq!(restored.arithmetic(), Arithmetic::Checked);
+        assert_eq!(restored.isa(), Isa::Day2);
+    }
+
+    #[test]
+    fn wide_values() {
+        // mul #MAX, #-4, [9]; seq [9], [9], [10]; hlt
+        let code = vec![1102, i64::MAX, -4, 9, 8, 9, 9, 10, 99, 0, 0];
+        let mut computer = Computer::new(code);
+        computer.set_arithmetic(Arithmetic::Big);
+        computer.step().unwrap();
+        assert_eq!(computer.wide.len(), 1);
+
+        let mut restored = Computer::from_snapshot(&computer.snapshot()).unwrap();
+        assert_eq!(restored.arithmetic(), Arithmetic::Big);
+        assert_eq!(restored.wide, computer.wide);
+        assert_eq!(restored.snapshot(), computer.snapshot());
+        assert_eq!(restored.run(), Action::Halt);
+        assert_eq!(restored.peek(10), 1);
+
+        // A wide value must not fit in 64 bits
+        let mut invalid = computer.clone();
+        invalid.wide.insert(9, BigInt::from(-4));
+        assert_eq!(
+            Computer::from_snapshot(&invalid.snapshot()).err(),
+            Some(SnapshotError::Invalid)
+        );
+    }
+
+    #[test]
+    fn sparse_memory() {
+        let mut computer = Computer::new(vec![99]);
+        computer.set_memory_limit(Some(1 << 20));
+        computer.poke(1 << 40, 42).unwrap();
+
+        let restored = Computer::from_snapshot(&computer.snapshot()).unwrap();
+        assert_eq!(restored.peek(1 << 40), 42);
+        assert_eq!(restored.memory().allocated(), computer.memory().allocated());
+        assert_eq!(restored.memory().limit(), Some(1 << 20));
+    }
+
+    #[test]
+    fn zero_page() {
+        let mut computer = Computer::new(vec![99]);
+        computer.poke(1 << 40, 42).unwrap();
+        computer.poke(1 << 40, 0).unwrap();
+
+        let restored = Computer::from_snapshot(&computer.snapshot()).unwrap();
+        assert_eq!(restored.memory().allocated(), computer.memory().allocated());
+    }
+
+    #[test]
+    fn corrupt_page_address() {
+        let misaligned = (1 << 40) + 1;
+        let dense = PAGE_SIZE as u64;
+        for &start in &[u64::MAX - 5, misaligned, dense] {
+            let mut bytes = MAGIC.to_vec();
+            for &value in &[2, 0, 0, 0, 0, 0, 1, start] {
+                write_varint(&mut bytes, value);
+            }
+            bytes.extend(vec![0; PAGE_SIZE + 1]);
+            assert_eq!(
+                Computer::from_snapshot(&bytes).err(),
+                Some(SnapshotError::Invalid)
+            );
+        }
+    }
+
+    #[test]
+    fn instruction_count() {
+        let mut computer = Computer::new(vec![104, 1, 99]);
+        assert_eq!(computer.run().output(), 1);
+
+        let restored = Computer::from_snapshot(&computer.snapshot()).unwrap();
+        assert_eq!(restored.instruction_count(), 1);
+    }
+}
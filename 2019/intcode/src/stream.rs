@@ -0,0 +1,330 @@
+//! Connecting a computer to whatever produces its input and consumes its output.
+//!
+//! Instead of providing input up front and handling one [`Action`] at a time, a computer can run
+//! against an [`InputSource`] that is asked for a value whenever the program needs one, and an
+//! [`OutputSink`] that receives every value it outputs. Closures, channels and vectors can be used
+//! directly, iterators through [`values`], and readers and writers of ASCII text through
+//! [`AsciiReader`] and [`AsciiWriter`].
+
+use crate::watch::Stop;
+use crate::{Action, Computer, IntcodeError};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+pub trait InputSource {
+    /// The next input value, or `None` if there is nothing to give the program right now.
+    fn next_input(&mut self) -> Option<i64>;
+}
+
+pub trait OutputSink {
+    fn output(&mut self, value: i64);
+}
+
+impl<F: FnMut() -> Option<i64>> InputSource for F {
+    fn next_input(&mut self) -> Option<i64> {
+        self()
+    }
+}
+
+impl InputSource for VecDeque<i64> {
+    fn next_input(&mut self) -> Option<i64> {
+        self.pop_front()
+    }
+}
+
+/// Waits for the next value. Runs out once every sender has been dropped.
+impl InputSource for Receiver<i64> {
+    fn next_input(&mut self) -> Option<i64> {
+        self.recv().ok()
+    }
+}
+
+impl<F: FnMut(i64)> OutputSink for F {
+    fn output(&mut self, value: i64) {
+        self(value)
+    }
+}
+
+impl OutputSink for Vec<i64> {
+    fn output(&mut self, value: i64) {
+        self.push(value);
+    }
+}
+
+/// Values sent after the receiver has been dropped are discarded.
+impl OutputSink for Sender<i64> {
+    fn output(&mut self, value: i64) {
+        let _ = self.send(value);
+    }
+}
+
+/// An input source that takes its values from an iterator.
+pub struct Values<I>(I);
+
+pub fn values<I: IntoIterator<Item = i64>>(values: I) -> Values<I::IntoIter> {
+    Values(values.into_iter())
+}
+
+impl<I: Iterator<Item = i64>> InputSource for Values<I> {
+    fn next_input(&mut self) -> Option<i64> {
+        self.0.next()
+    }
+}
+
+/// Provides every byte read from a reader as an input. The source runs out at the end of the
+/// reader, or when reading fails.
+pub struct AsciiReader<R> {
+    reader: R,
+    error: Option<io::Error>,
+}
+
+impl<R: BufRead> AsciiReader<R> {
+    pub fn new(reader: R) -> AsciiReader<R> {
+        AsciiReader {
+            reader,
+            error: None,
+        }
+    }
+
+    /// The error that stopped reading, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+}
+
+impl<R: BufRead> InputSource for AsciiReader<R> {
+    fn next_input(&mut self) -> Option<i64> {
+        if self.error.is_some() {
+            return None;
+        }
+        let byte = match self.reader.fill_buf() {
+            Ok(buffer) => *buffer.first()?,
+            Err(error) => {
+                self.error = Some(error);
+                return None;
+            }
+        };
+        self.reader.consume(1);
+        Some(i64::from(byte))
+    }
+}
+
+/// Writes outputs in the ASCII range as characters, and any other value as a number on its own
+/// line. The writer is flushed after every line.
+pub struct AsciiWriter<W> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> AsciiWriter<W> {
+    pub fn new(writer: W) -> AsciiWriter<W> {
+        AsciiWriter {
+            writer,
+            error: None,
+        }
+    }
+
+    /// The first error encountered while writing. Nothing more is written after an error.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write(&mut self, value: i64) -> io::Result<()> {
+        if (0..128).contains(&value) {
+            self.writer.write_all(&[value as u8])?;
+            if value == i64::from(b'\n') {
+                self.writer.flush()?;
+            }
+        } else {
+            writeln!(self.writer, "{}", value)?;
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> OutputSink for AsciiWriter<W> {
+    fn output(&mut self, value: i64) {
+        if self.error.is_none() {
+            self.error = self.write(value).err();
+        }
+    }
+}
+
+/// Borrows a separate source and sink as a single device.
+struct Split<'a, I, O> {
+    input: &'a mut I,
+    output: &'a mut O,
+}
+
+impl<I: InputSource, O: OutputSink> InputSource for Split<'_, I, O> {
+    fn next_input(&mut self) -> Option<i64> {
+        self.input.next_input()
+    }
+}
+
+impl<I: InputSource, O: OutputSink> OutputSink for Split<'_, I, O> {
+    fn output(&mut self, value: i64) {
+        self.output.output(value)
+    }
+}
+
+impl Computer {
+    /// Run until the program halts, or needs input that `input` can not provide. Input that has
+    /// already been provided to the computer is used first.
+    pub fn run_io(&mut self, input: &mut impl InputSource, output: &mut impl OutputSink) -> Action {
+        self.run_device(&mut Split { input, output })
+    }
+
+    pub fn try_run_io(
+        &mut self,
+        input: &mut impl InputSource,
+        output: &mut impl OutputSink,
+    ) -> Result<Action, IntcodeError> {
+        self.try_run_device(&mut Split { input, output })
+    }
+
+    /// Like [`Computer::run_io`], but with a single value that handles both input and output, for
+    /// when what the program is given depends on what it has output.
+    pub fn run_device<D: InputSource + OutputSink>(&mut self, device: &mut D) -> Action {
+        match self.try_run_device(device) {
+            Ok(action) => action,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    pub fn try_run_device<D: InputSource + OutputSink>(
+        &mut self,
+        device: &mut D,
+    ) -> Result<Action, IntcodeError> {
+        loop {
+            match self.try_run()? {
+                Action::Output(value) => device.output(value),
+                Action::NeedsInput => match device.next_input() {
+                    Some(value) => self.provide_input(Some(value)),
+                    None => return Ok(Action::NeedsInput),
+                },
+                action => return Ok(action),
+            }
+        }
+    }
+
+    /// Like [`Computer::run_io`], but also stops when a watchpoint fires, like
+    /// [`Computer::run_watched`].
+    pub fn run_io_watched(
+        &mut self,
+        input: &mut impl InputSource,
+        output: &mut impl OutputSink,
+    ) -> Result<Stop, IntcodeError> {
+        self.run_device_watched(&mut Split { input, output })
+    }
+
+    /// Like [`Computer::run_device`], but also stops when a watchpoint fires, like
+    /// [`Computer::run_watched`].
+    pub fn run_device_watched<D: InputSource + OutputSink>(
+        &mut self,
+        device: &mut D,
+    ) -> Result<Stop, IntcodeError> {
+        loop {
+            match self.run_watched()? {
+                Stop::Action(Action::Output(value)) => device.output(value),
+                Stop::Action(Action::NeedsInput) => match device.next_input() {
+                    Some(value) => self.provide_input(Some(value)),
+                    None => return Ok(Stop::Action(Action::NeedsInput)),
+                },
+                stop => return Ok(stop),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::program;
+    use crate::watch::{Hit, Watch};
+    use std::sync::mpsc::channel;
+
+    /// Outputs twice every input until it reads a zero.
+    const DOUBLE: &str = "
+        loop:   in [x]
+                jez [x], #done
+                mul [x], #2, [x]
+                out [x]
+                jnz #1, #loop
+        done:   hlt
+        x:      data 0
+    ";
+
+    #[test]
+    fn closures_and_iterators() {
+        let program = program(DOUBLE);
+
+        let mut computer = program.clone();
+        let mut next = 3;
+        let mut input = || {
+            next -= 1;
+            Some(next)
+        };
+        let mut output = Vec::new();
+        assert_eq!(computer.run_io(&mut input, &mut output), Action::Halt);
+        assert_eq!(output, vec![4, 2]);
+
+        let mut computer = program.clone();
+        let mut sum = 0;
+        let action = computer.run_io(&mut values(vec![5, 6]), &mut |value| sum += value);
+        assert_eq!(action, Action::NeedsInput);
+        assert_eq!(sum, 22);
+    }
+
+    #[test]
+    fn watchpoints() {
+        let mut computer = program(DOUBLE);
+        computer.add_watchpoint(15..=15, Watch::change(|_, new| new > 8));
+        let mut input = values(vec![3, 5, 0]);
+        let mut output = Vec::new();
+
+        let stop = computer.run_io_watched(&mut input, &mut output).unwrap();
+        let hit = Hit {
+            address: 15,
+            old: 5,
+            new: 10,
+        };
+        assert_eq!(stop, Stop::Watchpoint(hit));
+        assert_eq!(output, vec![6]);
+
+        let stop = computer.run_io_watched(&mut input, &mut output).unwrap();
+        assert_eq!(stop, Stop::Action(Action::Halt));
+        assert_eq!(output, vec![6, 10]);
+    }
+
+    #[test]
+    fn channels() {
+        let (input, mut receiver) = channel();
+        let (mut sender, output) = channel();
+        input.send(21).unwrap();
+        input.send(0).unwrap();
+
+        let mut computer = program(DOUBLE);
+        assert_eq!(computer.run_io(&mut receiver, &mut sender), Action::Halt);
+        assert_eq!(output.try_iter().collect::<Vec<_>>(), vec![42]);
+    }
+
+    #[test]
+    fn ascii() {
+        let mut computer = program(DOUBLE);
+        let mut reader = AsciiReader::new(&b"\x05\x7f"[..]);
+        let mut writer = AsciiWriter::new(Vec::new());
+        assert_eq!(
+            computer.run_io(&mut reader, &mut writer),
+            Action::NeedsInput
+        );
+        assert!(reader.error().is_none());
+        assert_eq!(writer.into_inner(), b"\n254\n");
+    }
+}
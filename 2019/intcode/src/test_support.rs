@@ -0,0 +1,13 @@
+//! Programs and helpers shared by the tests of several modules. The programs are kept under
+//! `tests/fixtures`, so that the integration tests can use them too.
+
+use crate::asm::assemble;
+use crate::Computer;
+
+pub const CALLS: &str = include_str!("../tests/fixtures/calls.asm");
+pub const SUM: &str = include_str!("../tests/fixtures/sum.asm");
+
+/// Assemble `source` into a computer.
+pub fn program(source: &str) -> Computer {
+    Computer::new(assemble(source).unwrap())
+}
@@ -0,0 +1,341 @@
+//! Recording and replaying the input and output of a session.
+//!
+//! A transcript file has one event per line, `in <value>` or `out <value>`. Empty lines and lines
+//! starting with `#` are ignored. Stepping back with history enabled also removes the undone
+//! events from the transcript being recorded, so that it can still be replayed.
+
+use crate::{Action, Computer, IntcodeError, Observer, Result};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Event {
+    Input(i64),
+    Output(i64),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Transcript {
+    events: Vec<Event>,
+    /// Number of instructions executed before each event, used to undo events when stepping back.
+    steps: Vec<u64>,
+    executed: u64,
+}
+
+/// Where a replay stopped matching its transcript.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the event that was expected.
+    pub index: usize,
+    pub expected: Event,
+    /// What the program did instead: an output, a request for input, a halt or a fault.
+    pub actual: Result<Action, IntcodeError>,
+    /// Address of the instruction that diverged.
+    pub instruction: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    Diverged(Divergence),
+    /// The program executed the maximum number of instructions without reaching the event at
+    /// `index`.
+    StepLimit {
+        index: usize,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ParseTranscriptError {
+    pub line: usize,
+}
+
+impl Transcript {
+    pub fn new() -> Transcript {
+        Transcript::default()
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn push(&mut self, event: Event) {
+        self.events.push(event);
+        self.steps.push(self.executed);
+    }
+
+    /// Every value that was output, in order.
+    pub fn outputs(&self) -> impl Iterator<Item = i64> + '_ {
+        self.events.iter().filter_map(|event| match *event {
+            Event::Output(value) => Some(value),
+            Event::Input(_) => None,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Transcript> {
+        Ok(fs::read_to_string(path)?.parse()?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Called before executing an instruction, with the number executed so far.
+    pub(crate) fn begin(&mut self, executed: u64) {
+        self.executed = executed;
+    }
+
+    /// Remove the events that happened after `executed` instructions.
+    pub(crate) fn undo(&mut self, executed: u64) {
+        let len = self
+            .steps
+            .iter()
+            .take_while(|&&step| step < executed)
+            .count();
+        self.events.truncate(len);
+        self.steps.truncate(len);
+    }
+}
+
+/// Transcripts are equal when they have the same events, however they were recorded.
+impl PartialEq for Transcript {
+    fn eq(&self, other: &Transcript) -> bool {
+        self.events == other.events
+    }
+}
+
+impl Eq for Transcript {}
+
+impl Observer for Transcript {
+    fn input(&mut self, value: i64) {
+        self.push(Event::Input(value));
+    }
+
+    fn output(&mut self, value: i64) {
+        self.push(Event::Output(value));
+    }
+}
+
+impl Computer {
+    /// Start or stop recording a transcript. Starting discards any previous transcript.
+    pub fn set_recording(&mut self, enabled: bool) {
+        self.transcript = if enabled {
+            Some(Transcript::new())
+        } else {
+            None
+        };
+    }
+
+    pub fn transcript(&self) -> Option<&Transcript> {
+        self.transcript.as_ref()
+    }
+
+    /// Run the program through every event of `transcript`, providing the recorded inputs and
+    /// checking that it outputs the recorded values. On success the computer is left just after
+    /// the last event, ready to continue the session. Fails with [`ReplayError::StepLimit`] if the
+    /// program executes `max_steps` instructions without reaching the next event.
+    pub fn replay(
+        &mut self,
+        transcript: &Transcript,
+        max_steps: Option<u64>,
+    ) -> Result<(), ReplayError> {
+        let mut events = transcript.events.iter().copied().enumerate();
+        let (mut index, mut expected) = match events.next() {
+            Some(event) => event,
+            None => return Ok(()),
+        };
+
+        let mut steps = 0;
+        loop {
+            if max_steps.is_some_and(|max| steps >= max) {
+                return Err(ReplayError::StepLimit { index });
+            }
+            steps += 1;
+
+            let instruction = self.instruction;
+            let actual = match self.step() {
+                Ok(Some(action)) => Ok(action),
+                Ok(None) => continue,
+                Err(error) => Err(error),
+            };
+            let matches = match (actual, expected) {
+                (Ok(Action::Output(value)), Event::Output(recorded)) => value == recorded,
+                (Ok(Action::NeedsInput), Event::Input(value)) => {
+                    self.provide_input(Some(value));
+                    true
+                }
+                _ => false,
+            };
+
+            if !matches {
+                return Err(ReplayError::Diverged(Divergence {
+                    index,
+                    expected,
+                    actual,
+                    instruction,
+                }));
+            }
+
+            match events.next() {
+                Some(event) => {
+                    index = event.0;
+                    expected = event.1;
+                    steps = 0;
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Transcript {
+    type Err = ParseTranscriptError;
+
+    fn from_str(text: &str) -> Result<Transcript, ParseTranscriptError> {
+        let mut transcript = Transcript::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = ParseTranscriptError { line: index + 1 };
+            let mut words = line.split_whitespace();
+            let kind = words.next().ok_or(error)?;
+            let value = words.next().and_then(|word| word.parse().ok());
+            let event = match (kind, value, words.next()) {
+                ("in", Some(value), None) => Event::Input(value),
+                ("out", Some(value), None) => Event::Output(value),
+                _ => return Err(error),
+            };
+            transcript.push(event);
+        }
+        Ok(transcript)
+    }
+}
+
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Input(value) => write!(f, "in {}", value),
+            Event::Output(value) => write!(f, "out {}", value),
+        }
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "replay diverged at event {} (instruction {}): expected `{}`, ",
+            self.index, self.instruction, self.expected
+        )?;
+        match self.actual {
+            Ok(Action::Output(value)) => write!(f, "but the program output {}", value),
+            Ok(Action::NeedsInput) => write!(f, "but the program needs input"),
+            Ok(Action::Halt) => write!(f, "but the program halted"),
+            Err(error) => write!(f, "but the program faulted: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Diverged(divergence) => divergence.fmt(f),
+            ReplayError::StepLimit { index } => {
+                write!(f, "replay reached the step limit before event {}", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl fmt::Display for ParseTranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid transcript event on line {}", self.line)
+    }
+}
+
+impl std::error::Error for ParseTranscriptError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{program, SUM};
+    use crate::Fault;
+
+    #[test]
+    fn record_and_replay() {
+        let program = program(SUM);
+
+        let mut computer = program.clone();
+        computer.set_recording(true);
+        computer.set_history(true);
+        computer.provide_input(vec![3, 4]);
+        assert_eq!(computer.run().output(), 3);
+        assert_eq!(computer.run().output(), 7);
+        assert_eq!(computer.run(), Action::NeedsInput);
+        assert!(computer.run_back_to(2));
+        let transcript = computer.transcript().unwrap().clone();
+        assert_eq!(transcript.to_string(), "in 3\nout 3\n");
+        assert_eq!(transcript.to_string().parse(), Ok(transcript.clone()));
+
+        let mut replay = program.clone();
+        assert_eq!(replay.replay(&transcript, None), Ok(()));
+        assert_eq!(replay.instruction_pointer(), 13);
+        replay.provide_input(Some(10));
+        assert_eq!(replay.run().output(), 13);
+
+        let diverging = "in 3\nout 3\nin 5\n# comment\nout 9\n".parse().unwrap();
+        assert_eq!(
+            program.clone().replay(&diverging, None),
+            Err(ReplayError::Diverged(Divergence {
+                index: 3,
+                expected: Event::Output(9),
+                actual: Ok(Action::Output(8)),
+                instruction: 11,
+            }))
+        );
+
+        let mut faulting = program.clone();
+        faulting.poke(4, 0).unwrap();
+        let divergence = match faulting.replay(&transcript, None) {
+            Err(ReplayError::Diverged(divergence)) => divergence,
+            result => panic!("unexpected {:?}", result),
+        };
+        assert_eq!(divergence.index, 1);
+        assert_eq!(divergence.instruction, 4);
+        assert_eq!(divergence.actual.unwrap_err().fault, Fault::InvalidOpcode);
+
+        assert_eq!(
+            "in 3\nout x".parse::<Transcript>(),
+            Err(ParseTranscriptError { line: 2 })
+        );
+    }
+
+    #[test]
+    fn step_limit() {
+        // Loops forever without any input or output
+        let mut computer = Computer::new(vec![1105, 1, 0]);
+        let transcript = "out 1\n".parse().unwrap();
+        assert_eq!(
+            computer.replay(&transcript, Some(1000)),
+            Err(ReplayError::StepLimit { index: 0 })
+        );
+        assert_eq!(computer.instruction_count(), 1000);
+    }
+}
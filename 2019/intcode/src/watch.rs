@@ -0,0 +1,241 @@
+//! Pausing execution when memory is accessed.
+//!
+//! Watchpoints only pause [`Computer::run_watched`], [`Computer::step_watched`],
+//! [`Computer::run_io_watched`] and [`Computer::run_device_watched`]. Every other way of running a
+//! computer, including [`Computer::run`], ignores them. When a watchpoint fires, the instruction that
+//! triggered it still completes, and [`Stop::Watchpoint`] is returned. Running again continues
+//! with the next instruction. If an `out` instruction triggers a watchpoint, its output is
+//! returned first.
+
+use crate::{Action, Computer, IntcodeError, Observer};
+use std::fmt;
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub enum Watch {
+    Read,
+    Write,
+    /// Writes that change the value of a cell, for which the predicate returns `true` when called
+    /// with the old and new value.
+    Change(Arc<dyn Fn(i64, i64) -> bool + Send + Sync>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WatchId(usize);
+
+/// Why [`Computer::run_watched`] returned.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stop {
+    Action(Action),
+    Watchpoint(Hit),
+}
+
+/// An access to a watched address. For reads, `old` and `new` are both the value read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Hit {
+    pub address: usize,
+    pub old: i64,
+    pub new: i64,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Watchpoints {
+    watches: Vec<(WatchId, usize, usize, Watch)>,
+    next_id: usize,
+    /// A watchpoint that fired during the current instruction.
+    hit: Option<Hit>,
+    /// A watchpoint that fired during an instruction that also produced an action.
+    pending: Option<Hit>,
+}
+
+impl Watch {
+    pub fn change(predicate: impl Fn(i64, i64) -> bool + Send + Sync + 'static) -> Watch {
+        Watch::Change(Arc::new(predicate))
+    }
+}
+
+impl Watchpoints {
+    fn fire(&mut self, address: usize, old: i64, new: i64, matches: impl Fn(&Watch) -> bool) {
+        if self.hit.is_some() {
+            return;
+        }
+        let watched = self
+            .watches
+            .iter()
+            .any(|(_, start, end, watch)| (*start..=*end).contains(&address) && matches(watch));
+        if watched {
+            self.hit = Some(Hit { address, old, new });
+        }
+    }
+}
+
+impl Observer for Watchpoints {
+    fn read(&mut self, address: usize, value: i64) {
+        self.fire(address, value, value, |watch| matches!(watch, Watch::Read));
+    }
+
+    fn write(&mut self, address: usize, old: i64, new: i64) {
+        self.fire(address, old, new, |watch| match watch {
+            Watch::Read => false,
+            Watch::Write => true,
+            Watch::Change(predicate) => old != new && predicate(old, new),
+        });
+    }
+}
+
+impl Computer {
+    /// Watch a range of addresses. A single address can be watched with `address..=address`.
+    pub fn add_watchpoint(&mut self, addresses: impl RangeBounds<usize>, watch: Watch) -> WatchId {
+        let start = match addresses.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match addresses.end_bound() {
+            Bound::Included(&end) => end,
+            Bound::Excluded(&end) => end.saturating_sub(1),
+            Bound::Unbounded => usize::MAX,
+        };
+
+        let watchpoints = &mut self.watchpoints;
+        let id = WatchId(watchpoints.next_id);
+        watchpoints.next_id += 1;
+        watchpoints.watches.push((id, start, end, watch));
+        id
+    }
+
+    pub fn remove_watchpoint(&mut self, id: WatchId) {
+        self.watchpoints
+            .watches
+            .retain(|&(other, _, _, _)| other != id);
+    }
+
+    pub fn clear_watchpoints(&mut self) {
+        self.watchpoints.watches.clear();
+    }
+
+    /// Run until the next action, or until a watchpoint fires.
+    pub fn run_watched(&mut self) -> Result<Stop, IntcodeError> {
+        loop {
+            if let Some(stop) = self.step_watched()? {
+                return Ok(stop);
+            }
+        }
+    }
+
+    /// Execute a single instruction, like [`Computer::step`], and report any watchpoint it
+    /// triggered.
+    pub fn step_watched(&mut self) -> Result<Option<Stop>, IntcodeError> {
+        if let Some(hit) = self.watchpoints.pending.take() {
+            return Ok(Some(Stop::Watchpoint(hit)));
+        }
+
+        let mut watchpoints = mem::take(&mut self.watchpoints);
+        let result = self.step_with(&mut watchpoints);
+        let hit = watchpoints.hit.take();
+        self.watchpoints = watchpoints;
+
+        match result? {
+            Some(action) => {
+                if action != Action::NeedsInput {
+                    self.watchpoints.pending = hit;
+                }
+                Ok(Some(Stop::Action(action)))
+            }
+            None => Ok(hit.map(Stop::Watchpoint)),
+        }
+    }
+}
+
+impl fmt::Display for WatchId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::program;
+
+    /// Counts down from 3, printing the counter each time.
+    const COUNTDOWN: &str = "
+        loop:   add [n], #-1, [n]
+                out [n]
+                jnz [n], #loop
+                hlt
+        n:      data 3
+    ";
+
+    #[test]
+    fn write_and_read() {
+        let mut computer = program(COUNTDOWN);
+        computer.add_watchpoint(10..=10, Watch::Write);
+
+        let mut stops = Vec::new();
+        loop {
+            match computer.run_watched().unwrap() {
+                Stop::Action(Action::Halt) => break,
+                stop => stops.push(stop),
+            }
+        }
+        let write = |old, new| {
+            Stop::Watchpoint(Hit {
+                address: 10,
+                old,
+                new,
+            })
+        };
+        assert_eq!(
+            stops,
+            vec![
+                write(3, 2),
+                Stop::Action(Action::Output(2)),
+                write(2, 1),
+                Stop::Action(Action::Output(1)),
+                write(1, 0),
+                Stop::Action(Action::Output(0)),
+            ]
+        );
+
+        // Other ways of running ignore watchpoints
+        let mut computer = program(COUNTDOWN);
+        computer.add_watchpoint(10..=10, Watch::Write);
+        assert_eq!(computer.run(), Action::Output(2));
+
+        // An output that triggers a watchpoint is returned first
+        let mut computer = program(COUNTDOWN);
+        let id = computer.add_watchpoint(10.., Watch::Read);
+        let mut run = || computer.run_watched().unwrap();
+        let read = |value| {
+            Stop::Watchpoint(Hit {
+                address: 10,
+                old: value,
+                new: value,
+            })
+        };
+        assert_eq!(run(), read(3));
+        assert_eq!(run(), Stop::Action(Action::Output(2)));
+        assert_eq!(run(), read(2));
+        assert_eq!(run(), read(2));
+
+        computer.remove_watchpoint(id);
+        assert_eq!(computer.run_watched(), Ok(Stop::Action(Action::Output(1))));
+    }
+
+    #[test]
+    fn change_predicate() {
+        let mut computer = program(COUNTDOWN);
+        computer.add_watchpoint(..100, Watch::change(|_, new| new == 0));
+        let outputs = std::iter::from_fn(|| match computer.run_watched().unwrap() {
+            Stop::Action(Action::Output(value)) => Some(value),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+        assert_eq!(outputs, vec![2, 1]);
+        assert_eq!(computer.instruction_pointer(), 4);
+        assert_eq!(computer.run().output(), 0);
+    }
+}
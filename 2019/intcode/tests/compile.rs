@@ -0,0 +1,143 @@
+//! Runs programs compiled by `intcode::compile` against the interpreter.
+//!
+//! The compiled sources are checked in under `tests/compiled`. After changing the code generator,
+//! regenerate them with `INTCODE_BLESS=1 cargo test --test compile`.
+
+use intcode::asm::assemble;
+use intcode::compile::compile;
+use intcode::{Action, Computer, Fault};
+use std::env;
+use std::fs;
+
+include!("compiled/quine.rs");
+include!("compiled/countdown.rs");
+include!("compiled/calls.rs");
+include!("compiled/far_input.rs");
+
+const QUINE: &[i64] = &[
+    109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+];
+
+/// Counts down from 3, patching its own output instruction to print the counter.
+const COUNTDOWN: &[i64] = &[
+    1001, 17, -1, 17, 1001, 17, 0, 12, 1105, 1, 11, 104, 0, 1005, 17, 0, 99, 3,
+];
+
+const CALLS: &str = include_str!("fixtures/calls.asm");
+
+/// Echoes one value through a cell far past the end of the program.
+const FAR_INPUT: &str = "
+    in [1000000000000]
+    out [1000000000000]
+    hlt
+";
+
+fn interpret(mut computer: Computer) -> (Vec<i64>, u64) {
+    let mut output = Vec::new();
+    while let Action::Output(value) = computer.run() {
+        output.push(value);
+    }
+    (output, computer.instruction_count())
+}
+
+macro_rules! native {
+    ($program:ident, $computer:expr) => {{
+        let mut program = $program::new($computer);
+        let mut output = Vec::new();
+        while let Action::Output(value) = program.run() {
+            output.push(value);
+        }
+        assert!(program.computer().is_halted());
+        (output, program.computer().instruction_count())
+    }};
+}
+
+#[test]
+fn quine() {
+    let computer = Computer::new(QUINE.to_vec());
+    let (output, count) = native!(Quine, computer.clone());
+    assert_eq!(output, QUINE);
+    assert_eq!((output, count), interpret(computer));
+}
+
+#[test]
+fn self_modifying() {
+    let computer = Computer::new(COUNTDOWN.to_vec());
+    let (output, count) = native!(Countdown, computer.clone());
+    assert_eq!(output, vec![2, 1, 0]);
+    assert_eq!((output, count), interpret(computer));
+}
+
+#[test]
+fn computed_jumps() {
+    let mut computer = Computer::new(assemble(CALLS).unwrap());
+    computer.provide_input(vec![3, -7, 12, 0]);
+    let (output, count) = native!(Calls, computer.clone());
+    assert_eq!(output, vec![9, 49, 144]);
+    assert_eq!((output, count), interpret(computer));
+}
+
+#[test]
+fn patched_before_running() {
+    // Turn the `mul` in `square` into an `add`, like day 2 patches its program before running it
+    let mut computer = Computer::new(assemble(CALLS).unwrap());
+    assert_eq!(computer.peek(26), 22202);
+    computer.poke(26, 22201).unwrap();
+    computer.provide_input(vec![3, 0]);
+    let (output, count) = native!(Calls, computer.clone());
+    assert_eq!(output, vec![6]);
+    assert_eq!((output, count), interpret(computer));
+}
+
+#[test]
+fn needs_input() {
+    let mut program = Calls::new(Computer::new(assemble(CALLS).unwrap()));
+    assert!(matches!(program.run(), Action::NeedsInput));
+    program.provide_input(Some(5));
+    assert_eq!(program.run().output(), 25);
+    assert!(matches!(program.run(), Action::NeedsInput));
+}
+
+#[test]
+fn out_of_memory_keeps_input() {
+    let mut computer = Computer::new(assemble(FAR_INPUT).unwrap());
+    computer.set_memory_limit(Some(100));
+    computer.provide_input(Some(7));
+
+    let mut program = FarInput::new(computer.clone());
+    let error = program.try_run().unwrap_err();
+    assert_eq!(error.fault, Fault::OutOfMemory(1_000_000_000_000));
+    assert_eq!(program.computer().pending_input(), 1);
+
+    let error = computer.try_run().unwrap_err();
+    assert_eq!(error.fault, Fault::OutOfMemory(1_000_000_000_000));
+    assert_eq!(computer.pending_input(), 1);
+
+    let mut computer = program.into_inner();
+    computer.set_memory_limit(None);
+    assert_eq!(computer.run().output(), 7);
+}
+
+#[test]
+fn generated_sources_are_current() {
+    let programs = [
+        ("quine", "Quine", QUINE.to_vec()),
+        ("countdown", "Countdown", COUNTDOWN.to_vec()),
+        ("calls", "Calls", assemble(CALLS).unwrap()),
+        ("far_input", "FarInput", assemble(FAR_INPUT).unwrap()),
+    ];
+
+    for (file, name, code) in &programs {
+        let path = format!("{}/tests/compiled/{}.rs", env!("CARGO_MANIFEST_DIR"), file);
+        let source = compile(code, name);
+        if env::var_os("INTCODE_BLESS").is_some() {
+            fs::write(&path, &source).unwrap();
+        }
+        let existing = fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            existing == source,
+            "{} is out of date, regenerate it with INTCODE_BLESS=1",
+            path
+        );
+    }
+}
@@ -0,0 +1,166 @@
+// Generated by intcode::compile. Do not edit.
+
+pub struct Calls {
+    computer: ::intcode::Computer,
+    dirty: bool,
+}
+
+#[allow(clippy::all, unreachable_code, unused_mut, unused_variables)]
+impl Calls {
+    pub fn new(computer: ::intcode::Computer) -> Calls {
+        let (image, code) = Calls::image();
+        let dirty = (0..image.len()).any(|i| code[i] && computer.peek(i) != image[i]);
+        Calls { computer, dirty }
+    }
+
+    pub fn computer(&self) -> &::intcode::Computer {
+        &self.computer
+    }
+
+    pub fn into_inner(self) -> ::intcode::Computer {
+        self.computer
+    }
+
+    pub fn provide_input(&mut self, input: impl IntoIterator<Item = i64>) {
+        self.computer.provide_input(input);
+    }
+
+    pub fn run(&mut self) -> ::intcode::Action {
+        match self.try_run() {
+            Ok(action) => action,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    pub fn try_run(&mut self) -> Result<::intcode::Action, ::intcode::IntcodeError> {
+        use ::intcode::compile::runtime;
+        use ::intcode::Action;
+
+        let (image, code) = Calls::image();
+        let c = &mut self.computer;
+        let dirty = &mut self.dirty;
+        if c.is_halted() || runtime::observed(c) {
+            return c.try_run();
+        }
+
+        let mut ip = c.instruction_pointer();
+        let mut rb = c.relative_base();
+        let mut executed = 0u64;
+
+        loop {
+            match ip {
+                0 => {
+                    if *dirty && (c.peek(0) != 109 || c.peek(1) != 36) { break; }
+                    let a: i64 = 36;
+                    rb = rb.wrapping_add(a);
+                    ip = 2;
+                    executed += 1;
+                }
+                2 => {
+                    if *dirty && (c.peek(2) != 3 || c.peek(3) != 35) { break; }
+                    let i: usize = 35;
+                    let v = match runtime::next_input(c) { Some(v) => v, None => { runtime::sync(c, 2, rb, executed); return Ok(Action::NeedsInput); } };
+                    if let Err(fault) = c.poke(i, v) { return Err(runtime::fault(c, 2, rb, executed, fault)); }
+                    runtime::take_input(c);
+                    ip = 4;
+                    executed += 1;
+                }
+                4 => {
+                    if *dirty && (c.peek(4) != 1006 || c.peek(5) != 35 || c.peek(6) != 23) { break; }
+                    let a: usize = 35;
+                    let a = c.peek(a);
+                    if a == 0 { let t: i64 = 23; if t < 0 { break; } ip = t as usize; } else { ip = 7; }
+                    executed += 1;
+                }
+                7 => {
+                    if *dirty && (c.peek(7) != 21101 || c.peek(8) != 18 || c.peek(9) != 0 || c.peek(10) != 0) { break; }
+                    let a: i64 = 18;
+                    let b: i64 = 0;
+                    let v = a.wrapping_add(b);
+                    let i = match rb.checked_add(0) { Some(i) if i >= 0 => i as usize, _ => break };
+                    if let Err(fault) = c.poke(i, v) { return Err(runtime::fault(c, 7, rb, executed, fault)); }
+                    if i < code.len() && code[i] && v != image[i] { *dirty = true; }
+                    ip = 11;
+                    executed += 1;
+                }
+                11 => {
+                    if *dirty && (c.peek(11) != 21001 || c.peek(12) != 35 || c.peek(13) != 0 || c.peek(14) != 1) { break; }
+                    let a: usize = 35;
+                    let a = c.peek(a);
+                    let b: i64 = 0;
+                    let v = a.wrapping_add(b);
+                    let i = match rb.checked_add(1) { Some(i) if i >= 0 => i as usize, _ => break };
+                    if let Err(fault) = c.poke(i, v) { return Err(runtime::fault(c, 11, rb, executed, fault)); }
+                    if i < code.len() && code[i] && v != image[i] { *dirty = true; }
+                    ip = 15;
+                    executed += 1;
+                }
+                15 => {
+                    if *dirty && (c.peek(15) != 1105 || c.peek(16) != 1 || c.peek(17) != 24) { break; }
+                    let a: i64 = 1;
+                    if a != 0 { let t: i64 = 24; if t < 0 { break; } ip = t as usize; } else { ip = 18; }
+                    executed += 1;
+                }
+                18 => {
+                    if *dirty && (c.peek(18) != 204 || c.peek(19) != 1) { break; }
+                    let v = match rb.checked_add(1) { Some(v) if v >= 0 => v as usize, _ => break };
+                    let v = c.peek(v);
+                    runtime::sync(c, 20, rb, executed + 1); return Ok(Action::Output(v));
+                }
+                20 => {
+                    if *dirty && (c.peek(20) != 1105 || c.peek(21) != 1 || c.peek(22) != 2) { break; }
+                    let a: i64 = 1;
+                    if a != 0 { let t: i64 = 2; if t < 0 { break; } ip = t as usize; } else { ip = 23; }
+                    executed += 1;
+                }
+                23 => {
+                    if *dirty && (c.peek(23) != 99) { break; }
+                    break;
+                }
+                24 => {
+                    if *dirty && (c.peek(24) != 109 || c.peek(25) != 1) { break; }
+                    let a: i64 = 1;
+                    rb = rb.wrapping_add(a);
+                    ip = 26;
+                    executed += 1;
+                }
+                26 => {
+                    if *dirty && (c.peek(26) != 22202 || c.peek(27) != 0 || c.peek(28) != 0 || c.peek(29) != 0) { break; }
+                    let a = match rb.checked_add(0) { Some(a) if a >= 0 => a as usize, _ => break };
+                    let a = c.peek(a);
+                    let b = match rb.checked_add(0) { Some(b) if b >= 0 => b as usize, _ => break };
+                    let b = c.peek(b);
+                    let v = a.wrapping_mul(b);
+                    let i = match rb.checked_add(0) { Some(i) if i >= 0 => i as usize, _ => break };
+                    if let Err(fault) = c.poke(i, v) { return Err(runtime::fault(c, 26, rb, executed, fault)); }
+                    if i < code.len() && code[i] && v != image[i] { *dirty = true; }
+                    ip = 30;
+                    executed += 1;
+                }
+                30 => {
+                    if *dirty && (c.peek(30) != 109 || c.peek(31) != -1) { break; }
+                    let a: i64 = -1;
+                    rb = rb.wrapping_add(a);
+                    ip = 32;
+                    executed += 1;
+                }
+                32 => {
+                    if *dirty && (c.peek(32) != 2106 || c.peek(33) != 0 || c.peek(34) != 0) { break; }
+                    let a: i64 = 0;
+                    if a == 0 { let t = match rb.checked_add(0) { Some(t) if t >= 0 => t as usize, _ => break }; let t = c.peek(t); if t < 0 { break; } ip = t as usize; } else { ip = 35; }
+                    executed += 1;
+                }
+_ => break,
+            }
+        }
+
+        runtime::sync(c, ip, rb, executed);
+        c.try_run()
+    }
+
+    fn image() -> (&'static [i64], &'static [bool]) {
+        static IMAGE: [i64; 38] = [109, 36, 3, 35, 1006, 35, 23, 21101, 18, 0, 0, 21001, 35, 0, 1, 1105, 1, 24, 204, 1, 1105, 1, 2, 99, 109, 1, 22202, 0, 0, 0, 109, -1, 2106, 0, 0, 0, 0, 0];
+        static CODE: [bool; 38] = [true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, false, false, false];
+        (&IMAGE, &CODE)
+    }
+}
@@ -0,0 +1,110 @@
+// Generated by intcode::compile. Do not edit.
+
+pub struct Countdown {
+    computer: ::intcode::Computer,
+    dirty: bool,
+}
+
+#[allow(clippy::all, unreachable_code, unused_mut, unused_variables)]
+impl Countdown {
+    pub fn new(computer: ::intcode::Computer) -> Countdown {
+        let (image, code) = Countdown::image();
+        let dirty = (0..image.len()).any(|i| code[i] && computer.peek(i) != image[i]);
+        Countdown { computer, dirty }
+    }
+
+    pub fn computer(&self) -> &::intcode::Computer {
+        &self.computer
+    }
+
+    pub fn into_inner(self) -> ::intcode::Computer {
+        self.computer
+    }
+
+    pub fn provide_input(&mut self, input: impl IntoIterator<Item = i64>) {
+        self.computer.provide_input(input);
+    }
+
+    pub fn run(&mut self) -> ::intcode::Action {
+        match self.try_run() {
+            Ok(action) => action,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    pub fn try_run(&mut self) -> Result<::intcode::Action, ::intcode::IntcodeError> {
+        use ::intcode::compile::runtime;
+        use ::intcode::Action;
+
+        let (image, code) = Countdown::image();
+        let c = &mut self.computer;
+        let dirty = &mut self.dirty;
+        if c.is_halted() || runtime::observed(c) {
+            return c.try_run();
+        }
+
+        let mut ip = c.instruction_pointer();
+        let mut rb = c.relative_base();
+        let mut executed = 0u64;
+
+        loop {
+            match ip {
+                0 => {
+                    if *dirty && (c.peek(0) != 1001 || c.peek(1) != 17 || c.peek(2) != -1 || c.peek(3) != 17) { break; }
+                    let a: usize = 17;
+                    let a = c.peek(a);
+                    let b: i64 = -1;
+                    let v = a.wrapping_add(b);
+                    let i: usize = 17;
+                    if let Err(fault) = c.poke(i, v) { return Err(runtime::fault(c, 0, rb, executed, fault)); }
+                    ip = 4;
+                    executed += 1;
+                }
+                4 => {
+                    if *dirty && (c.peek(4) != 1001 || c.peek(5) != 17 || c.peek(6) != 0 || c.peek(7) != 12) { break; }
+                    let a: usize = 17;
+                    let a = c.peek(a);
+                    let b: i64 = 0;
+                    let v = a.wrapping_add(b);
+                    let i: usize = 12;
+                    if let Err(fault) = c.poke(i, v) { return Err(runtime::fault(c, 4, rb, executed, fault)); }
+                    if v != 0 { *dirty = true; }
+                    ip = 8;
+                    executed += 1;
+                }
+                8 => {
+                    if *dirty && (c.peek(8) != 1105 || c.peek(9) != 1 || c.peek(10) != 11) { break; }
+                    let a: i64 = 1;
+                    if a != 0 { let t: i64 = 11; if t < 0 { break; } ip = t as usize; } else { ip = 11; }
+                    executed += 1;
+                }
+                11 => {
+                    if *dirty && (c.peek(11) != 104 || c.peek(12) != 0) { break; }
+                    let v: i64 = 0;
+                    runtime::sync(c, 13, rb, executed + 1); return Ok(Action::Output(v));
+                }
+                13 => {
+                    if *dirty && (c.peek(13) != 1005 || c.peek(14) != 17 || c.peek(15) != 0) { break; }
+                    let a: usize = 17;
+                    let a = c.peek(a);
+                    if a != 0 { let t: i64 = 0; if t < 0 { break; } ip = t as usize; } else { ip = 16; }
+                    executed += 1;
+                }
+                16 => {
+                    if *dirty && (c.peek(16) != 99) { break; }
+                    break;
+                }
+_ => break,
+            }
+        }
+
+        runtime::sync(c, ip, rb, executed);
+        c.try_run()
+    }
+
+    fn image() -> (&'static [i64], &'static [bool]) {
+        static IMAGE: [i64; 18] = [1001, 17, -1, 17, 1001, 17, 0, 12, 1105, 1, 11, 104, 0, 1005, 17, 0, 99, 3];
+        static CODE: [bool; 18] = [true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, false];
+        (&IMAGE, &CODE)
+    }
+}
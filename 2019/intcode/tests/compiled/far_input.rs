@@ -0,0 +1,84 @@
+// Generated by intcode::compile. Do not edit.
+
+pub struct FarInput {
+    computer: ::intcode::Computer,
+    dirty: bool,
+}
+
+#[allow(clippy::all, unreachable_code, unused_mut, unused_variables)]
+impl FarInput {
+    pub fn new(computer: ::intcode::Computer) -> FarInput {
+        let (image, code) = FarInput::image();
+        let dirty = (0..image.len()).any(|i| code[i] && computer.peek(i) != image[i]);
+        FarInput { computer, dirty }
+    }
+
+    pub fn computer(&self) -> &::intcode::Computer {
+        &self.computer
+    }
+
+    pub fn into_inner(self) -> ::intcode::Computer {
+        self.computer
+    }
+
+    pub fn provide_input(&mut self, input: impl IntoIterator<Item = i64>) {
+        self.computer.provide_input(input);
+    }
+
+    pub fn run(&mut self) -> ::intcode::Action {
+        match self.try_run() {
+            Ok(action) => action,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    pub fn try_run(&mut self) -> Result<::intcode::Action, ::intcode::IntcodeError> {
+        use ::intcode::compile::runtime;
+        use ::intcode::Action;
+
+        let (image, code) = FarInput::image();
+        let c = &mut self.computer;
+        let dirty = &mut self.dirty;
+        if c.is_halted() || runtime::observed(c) {
+            return c.try_run();
+        }
+
+        let mut ip = c.instruction_pointer();
+        let mut rb = c.relative_base();
+        let mut executed = 0u64;
+
+        loop {
+            match ip {
+                0 => {
+                    if *dirty && (c.peek(0) != 3 || c.peek(1) != 1000000000000) { break; }
+                    let i: usize = 1000000000000;
+                    let v = match runtime::next_input(c) { Some(v) => v, None => { runtime::sync(c, 0, rb, executed); return Ok(Action::NeedsInput); } };
+                    if let Err(fault) = c.poke(i, v) { return Err(runtime::fault(c, 0, rb, executed, fault)); }
+                    runtime::take_input(c);
+                    ip = 2;
+                    executed += 1;
+                }
+                2 => {
+                    if *dirty && (c.peek(2) != 4 || c.peek(3) != 1000000000000) { break; }
+                    let v: usize = 1000000000000;
+                    let v = c.peek(v);
+                    runtime::sync(c, 4, rb, executed + 1); return Ok(Action::Output(v));
+                }
+                4 => {
+                    if *dirty && (c.peek(4) != 99) { break; }
+                    break;
+                }
+_ => break,
+            }
+        }
+
+        runtime::sync(c, ip, rb, executed);
+        c.try_run()
+    }
+
+    fn image() -> (&'static [i64], &'static [bool]) {
+        static IMAGE: [i64; 5] = [3, 1000000000000, 4, 1000000000000, 99];
+        static CODE: [bool; 5] = [true, true, true, true, true];
+        (&IMAGE, &CODE)
+    }
+}
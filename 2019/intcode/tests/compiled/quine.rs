@@ -0,0 +1,111 @@
+// Generated by intcode::compile. Do not edit.
+
+pub struct Quine {
+    computer: ::intcode::Computer,
+    dirty: bool,
+}
+
+#[allow(clippy::all, unreachable_code, unused_mut, unused_variables)]
+impl Quine {
+    pub fn new(computer: ::intcode::Computer) -> Quine {
+        let (image, code) = Quine::image();
+        let dirty = (0..image.len()).any(|i| code[i] && computer.peek(i) != image[i]);
+        Quine { computer, dirty }
+    }
+
+    pub fn computer(&self) -> &::intcode::Computer {
+        &self.computer
+    }
+
+    pub fn into_inner(self) -> ::intcode::Computer {
+        self.computer
+    }
+
+    pub fn provide_input(&mut self, input: impl IntoIterator<Item = i64>) {
+        self.computer.provide_input(input);
+    }
+
+    pub fn run(&mut self) -> ::intcode::Action {
+        match self.try_run() {
+            Ok(action) => action,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    pub fn try_run(&mut self) -> Result<::intcode::Action, ::intcode::IntcodeError> {
+        use ::intcode::compile::runtime;
+        use ::intcode::Action;
+
+        let (image, code) = Quine::image();
+        let c = &mut self.computer;
+        let dirty = &mut self.dirty;
+        if c.is_halted() || runtime::observed(c) {
+            return c.try_run();
+        }
+
+        let mut ip = c.instruction_pointer();
+        let mut rb = c.relative_base();
+        let mut executed = 0u64;
+
+        loop {
+            match ip {
+                0 => {
+                    if *dirty && (c.peek(0) != 109 || c.peek(1) != 1) { break; }
+                    let a: i64 = 1;
+                    rb = rb.wrapping_add(a);
+                    ip = 2;
+                    executed += 1;
+                }
+                2 => {
+                    if *dirty && (c.peek(2) != 204 || c.peek(3) != -1) { break; }
+                    let v = match rb.checked_add(-1) { Some(v) if v >= 0 => v as usize, _ => break };
+                    let v = c.peek(v);
+                    runtime::sync(c, 4, rb, executed + 1); return Ok(Action::Output(v));
+                }
+                4 => {
+                    if *dirty && (c.peek(4) != 1001 || c.peek(5) != 100 || c.peek(6) != 1 || c.peek(7) != 100) { break; }
+                    let a: usize = 100;
+                    let a = c.peek(a);
+                    let b: i64 = 1;
+                    let v = a.wrapping_add(b);
+                    let i: usize = 100;
+                    if let Err(fault) = c.poke(i, v) { return Err(runtime::fault(c, 4, rb, executed, fault)); }
+                    ip = 8;
+                    executed += 1;
+                }
+                8 => {
+                    if *dirty && (c.peek(8) != 1008 || c.peek(9) != 100 || c.peek(10) != 16 || c.peek(11) != 101) { break; }
+                    let a: usize = 100;
+                    let a = c.peek(a);
+                    let b: i64 = 16;
+                    let v = (a == b) as i64;
+                    let i: usize = 101;
+                    if let Err(fault) = c.poke(i, v) { return Err(runtime::fault(c, 8, rb, executed, fault)); }
+                    ip = 12;
+                    executed += 1;
+                }
+                12 => {
+                    if *dirty && (c.peek(12) != 1006 || c.peek(13) != 101 || c.peek(14) != 0) { break; }
+                    let a: usize = 101;
+                    let a = c.peek(a);
+                    if a == 0 { let t: i64 = 0; if t < 0 { break; } ip = t as usize; } else { ip = 15; }
+                    executed += 1;
+                }
+                15 => {
+                    if *dirty && (c.peek(15) != 99) { break; }
+                    break;
+                }
+_ => break,
+            }
+        }
+
+        runtime::sync(c, ip, rb, executed);
+        c.try_run()
+    }
+
+    fn image() -> (&'static [i64], &'static [bool]) {
+        static IMAGE: [i64; 16] = [109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99];
+        static CODE: [bool; 16] = [true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true];
+        (&IMAGE, &CODE)
+    }
+}
@@ -0,0 +1,17 @@
+; Squares every input until it reads a zero, calling a function that keeps its frame relative to
+; the relative base and returns through a computed jump.
+        rb #stack
+loop:   in [n]
+        jez [n], #done
+        add #ret, #0, rb[0]
+        add [n], #0, rb[1]
+        jnz #1, #square
+ret:    out rb[1]
+        jnz #1, #loop
+done:   hlt
+square: rb #1
+        mul rb[0], rb[0], rb[0]
+        rb #-1
+        jez #0, rb[0]
+n:      data 0
+stack:  data 0, 0
@@ -0,0 +1,12 @@
+; Sums its inputs until it reads a zero, keeping the total relative to the relative base. Outputs
+; the total after every input, and once more before halting.
+        rb #total
+loop:   in [x]
+        jez [x], #done
+        add rb[0], [x], rb[0]
+        out rb[0]
+        jnz #1, #loop
+done:   out rb[0]
+        hlt
+x:      data 0
+total:  data 0